@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use database::search::{DictSearch, load_search, PronunciationType};
+use database::search::{DictSearch, load_search, Page, PronunciationType};
 
 pub fn benchmark_search(c: &mut Criterion) {
     let pool = database::create_db_pool();
@@ -8,15 +8,26 @@ pub fn benchmark_search(c: &mut Criterion) {
 
     println!("fetched from db");
     let mut search = DictSearch::new();
-    load_search(&mut search, connection);
+    load_search(&mut search, connection).unwrap();
 
     println!("indexing done");
 
+    let mut group = c.benchmark_group("index");
+    group.sample_size(10);
+    group.bench_function("load search", |b| {
+        b.iter(|| {
+            let mut search = DictSearch::new();
+            load_search(&mut search, connection).unwrap();
+            search
+        });
+    });
+    group.finish();
+
     c.bench_function("search pronunciation", |b| {
-        b.iter(|| search.search_pronunciation("? sei2", PronunciationType::Jyutping));
+        b.iter(|| search.search_pronunciation("? sei2", PronunciationType::Jyutping, Page::default()));
     });
     c.bench_function("search char", |b| {
-        b.iter(|| search.search_characters("?好"));
+        b.iter(|| search.search_characters("?好", Page::default()));
     });
 }
 
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- how common the word is, e.g. a count from a frequency list
+alter table word add column frequency integer not null default 0;
\ No newline at end of file
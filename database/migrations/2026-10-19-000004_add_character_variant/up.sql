@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- characters which are used interchangeably, e.g. 裏 and 裡, from the Unihan database
+create table character_variant(
+    character text not null,
+    variant text not null,
+    primary key (character, variant)
+);
\ No newline at end of file
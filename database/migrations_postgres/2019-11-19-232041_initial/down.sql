@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+drop table word_pronunciation;
+drop table word_entry;
+drop table word;
\ No newline at end of file
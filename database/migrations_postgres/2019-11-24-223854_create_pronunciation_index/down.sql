@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+drop index index_word_pronunciation_entry_id;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+create index index_word_pronunciation_entry_id on word_pronunciation (entry_id)
\ No newline at end of file
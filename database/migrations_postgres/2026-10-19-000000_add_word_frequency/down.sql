@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+alter table word drop column frequency;
\ No newline at end of file
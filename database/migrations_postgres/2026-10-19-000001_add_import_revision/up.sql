@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- incremented by every import, so that search index snapshots can be checked
+create table import_revision(
+    id integer primary key,
+    revision integer not null
+);
+insert into import_revision values (1, 0);
\ No newline at end of file
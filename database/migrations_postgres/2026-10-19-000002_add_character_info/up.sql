@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- the radical and stroke counts of each character, from the Unihan database
+create table character_info(
+    character text primary key,
+    radical integer not null,
+    residual_strokes integer not null,
+    total_strokes integer not null
+);
\ No newline at end of file
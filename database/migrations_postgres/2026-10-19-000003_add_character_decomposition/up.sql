@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- the Ideographic Description Sequence of each character, e.g. ⿰木木 for 林
+create table character_decomposition(
+    character text primary key,
+    decomposition text not null
+);
\ No newline at end of file
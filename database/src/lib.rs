@@ -1,13 +1,20 @@
 #[macro_use]
 pub extern crate diesel;
 
+#[cfg(not(feature = "postgres"))]
 use diesel::sqlite::SqliteConnection;
+#[cfg(feature = "postgres")]
+use diesel::pg::PgConnection;
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
 
 use dotenv::dotenv;
 use std::env;
+#[cfg(not(feature = "postgres"))]
 use diesel::connection::SimpleConnection;
 
+#[cfg(not(any(feature = "sqlite", feature = "postgres")))]
+compile_error!("either the `sqlite` or the `postgres` feature must be enabled");
+
 pub mod schema;
 pub mod models;
 pub mod search;
@@ -19,14 +26,107 @@ pub fn create_db_pool() -> ConnectionPool {
     ConnectionPool(Pool::builder().build(manager).expect(&format!("Could not create pool for database: {}", &url)))
 }
 
+/// The connection type of the selected database backend. PostgreSQL is used
+/// when the `postgres` feature is enabled, otherwise SQLite.
+#[cfg(not(feature = "postgres"))]
 pub type DbConnection = SqliteConnection;
+#[cfg(feature = "postgres")]
+pub type DbConnection = PgConnection;
+
+/// An error getting a connection from the pool.
+pub type PoolError = Box<dyn std::error::Error + Send + Sync>;
 
 #[derive(Clone)]
 pub struct ConnectionPool(Pool<ConnectionManager<DbConnection>>);
 impl ConnectionPool {
     pub fn get_connection(&self) -> PooledConnection<ConnectionManager<DbConnection>> {
-        let conn = self.0.clone().get().unwrap();
-        conn.batch_execute("pragma foreign_keys = on;").unwrap();
-        conn
+        self.try_get_connection().unwrap()
+    }
+    /// Get a connection, returning an error if the database is unavailable.
+    #[cfg(not(feature = "postgres"))]
+    pub fn try_get_connection(&self) -> Result<PooledConnection<ConnectionManager<DbConnection>>, PoolError> {
+        let conn = self.0.clone().get()?;
+        conn.batch_execute("pragma foreign_keys = on;")?;
+        Ok(conn)
+    }
+    /// Foreign keys are always enforced by PostgreSQL.
+    #[cfg(feature = "postgres")]
+    pub fn try_get_connection(&self) -> Result<PooledConnection<ConnectionManager<DbConnection>>, PoolError> {
+        Ok(self.0.clone().get()?)
+    }
+}
+
+/// The number of imports into the database. Search index snapshots built
+/// from an older revision are stale.
+pub fn import_revision(connection: &DbConnection) -> diesel::QueryResult<i32> {
+    use diesel::prelude::*;
+    use schema::import_revision;
+
+    import_revision::table.select(import_revision::revision).first(connection)
+}
+
+/// Record that data was imported. This should be run in the import's transaction.
+pub fn bump_import_revision(connection: &DbConnection) -> diesel::QueryResult<()> {
+    use diesel::prelude::*;
+    use schema::import_revision;
+
+    diesel::update(import_revision::table)
+        .set(import_revision::revision.eq(import_revision::revision + 1))
+        .execute(connection)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::{NewWord, Word};
+    use crate::schema::word;
+    use diesel::prelude::*;
+
+    // These tests need a migrated database at DATABASE_URL, so they are
+    // only run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn insert_and_load_word() {
+        let pool = create_db_pool();
+        let connection = &pool.get_connection();
+
+        connection.test_transaction::<_, diesel::result::Error, _>(|| {
+            // the database may already contain the word from an import
+            diesel::delete(word::table.filter(word::traditional.eq("垃圾桶"))).execute(connection)?;
+            diesel::insert_into(word::table)
+                .values(&NewWord {
+                    traditional: "垃圾桶".to_string(),
+                    simplified: "垃圾桶".to_string(),
+                })
+                .execute(connection)?;
+            let loaded = word::table
+                .filter(word::traditional.eq("垃圾桶"))
+                .first::<Word>(connection)?;
+            assert_eq!(loaded.simplified, "垃圾桶");
+            Ok(())
+        });
+    }
+    #[test]
+    #[ignore]
+    fn duplicate_word_rejected() {
+        let pool = create_db_pool();
+        let connection = &pool.get_connection();
+
+        connection.test_transaction::<_, diesel::result::Error, _>(|| {
+            let new_word = NewWord {
+                traditional: "好".to_string(),
+                simplified: "好".to_string(),
+            };
+            diesel::delete(word::table.filter(word::traditional.eq("好"))).execute(connection)?;
+            diesel::insert_into(word::table).values(&new_word).execute(connection)?;
+            // the unique constraint must hold on every backend
+            assert!(connection
+                .transaction::<_, diesel::result::Error, _>(|| {
+                    diesel::insert_into(word::table).values(&new_word).execute(connection)
+                })
+                .is_err());
+            Ok(())
+        });
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,4 @@
-use super::schema::{word, word_entry, word_pronunciation};
+use super::schema::{character_decomposition, character_info, character_variant, word, word_entry, word_pronunciation};
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -9,6 +9,7 @@ pub struct Word {
     pub word_id: i32,
     pub traditional: String,
     pub simplified: String,
+    pub frequency: i32,
 }
 #[derive(Insertable)]
 #[table_name = "word"]
@@ -53,3 +54,32 @@ pub struct NewWordPronunciation {
     pub pronunciation: String,
     pub entry_id: i32,
 }
+
+/// The radical and stroke counts of a character.
+#[derive(Queryable, Insertable, Debug, PartialEq)]
+#[table_name = "character_info"]
+pub struct CharacterInfo {
+    pub character: String,
+    /// The number of the Kangxi radical, from 1 to 214.
+    pub radical: i32,
+    /// The strokes other than the radical's.
+    pub residual_strokes: i32,
+    pub total_strokes: i32,
+}
+
+/// How a character is made of components, as an Ideographic Description Sequence.
+#[derive(Queryable, Insertable, Debug, PartialEq)]
+#[table_name = "character_decomposition"]
+pub struct CharacterDecomposition {
+    pub character: String,
+    /// e.g. "⿰木木" for 林
+    pub decomposition: String,
+}
+
+/// A pair of characters which are used interchangeably. Each pair is stored in both orders.
+#[derive(Queryable, Insertable, Debug, PartialEq)]
+#[table_name = "character_variant"]
+pub struct CharacterVariant {
+    pub character: String,
+    pub variant: String,
+}
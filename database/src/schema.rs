@@ -1,8 +1,39 @@
+table! {
+    character_decomposition (character) {
+        character -> Text,
+        decomposition -> Text,
+    }
+}
+
+table! {
+    character_info (character) {
+        character -> Text,
+        radical -> Integer,
+        residual_strokes -> Integer,
+        total_strokes -> Integer,
+    }
+}
+
+table! {
+    character_variant (character, variant) {
+        character -> Text,
+        variant -> Text,
+    }
+}
+
+table! {
+    import_revision (id) {
+        id -> Integer,
+        revision -> Integer,
+    }
+}
+
 table! {
     word (word_id) {
         word_id -> Integer,
         traditional -> Text,
         simplified -> Text,
+        frequency -> Integer,
     }
 }
 
@@ -28,6 +59,10 @@ joinable!(word_entry -> word (word_id));
 joinable!(word_pronunciation -> word_entry (entry_id));
 
 allow_tables_to_appear_in_same_query!(
+    character_decomposition,
+    character_info,
+    character_variant,
+    import_revision,
     word,
     word_entry,
     word_pronunciation,
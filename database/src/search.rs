@@ -1,19 +1,58 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 
-use crate::models::{Word, WordPronunciation};
+use crate::models::{CharacterDecomposition, CharacterInfo, CharacterVariant, Word, WordEntry, WordPronunciation};
 use crate::DbConnection;
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_while, take_while1, take_while_m_n};
-use nom::character::complete::anychar;
+use nom::character::complete::{anychar, multispace0};
 use nom::combinator::map;
 use nom::error::{ErrorKind, ParseError};
 use nom::multi::many0;
-use nom::sequence::pair;
+use nom::sequence::{pair, preceded};
 use nom::{Err, IResult};
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
 
+use self::detect::has_tone_marks;
+use self::english::{split_senses, tokenise_english};
+use self::fuzzy::fuzzy_sounds;
+use self::pinyin::normalise_pinyin;
+use self::suggest::Trie;
+use self::syllables::{segment, MAX_SEGMENTATIONS};
+
+mod components;
+mod contains;
+mod convert;
+mod detect;
+mod english;
+mod fuzzy;
+mod homophones;
+mod pinyin;
+mod radicals;
+mod segment;
+mod snapshot;
+mod suggest;
+mod syllables;
+mod variants;
+
+pub use self::components::{ComponentSearch, MAX_COMPONENTS};
+pub use self::convert::{convert, Romanization};
+pub use self::detect::{detect_query, QueryKind};
+pub use self::fuzzy::Merger;
+pub use self::homophones::{Homophone, Homophony};
+pub use self::radicals::{radicals, Radical, StrokeInfo};
+pub use self::segment::{Segment, MAX_SEGMENT_TEXT};
+pub use self::snapshot::{load_snapshot, save_snapshot, SnapshotError};
+
 type Id = i32;
+// an index into the interned syllables or English terms
+type InternId = u32;
+
+/// The most suggestions kept for each prefix.
+pub const MAX_SUGGESTIONS: usize = 10;
 
 impl Error for SearchError {}
 
@@ -30,258 +69,944 @@ impl Display for SearchError {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone)]
 enum QueryToken {
     WildcardSingle,
+    WildcardMulti,
     Character(char),
     Pronunciation { sound: String, tone: String },
 }
 
-#[derive(Eq, PartialEq, Hash, Debug, Copy, Clone)]
+#[derive(Eq, PartialEq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum PronunciationType {
     Pinyin,
     Jyutping,
 }
 
 impl PronunciationType {
-    fn from_integer(i: i32) -> Option<Self> {
+    pub fn from_integer(i: i32) -> Option<Self> {
         match i {
             0 => Some(Self::Pinyin),
             1 => Some(Self::Jyutping),
             _ => None,
         }
     }
+    /// The value stored in the `pronunciation_type` column.
+    pub fn to_integer(self) -> i32 {
+        match self {
+            Self::Pinyin => 0,
+            Self::Jyutping => 1,
+        }
+    }
+    /// Convert a pronunciation or query to the form stored in the index.
+    /// Pinyin may be written with tone marks, and ü may be written as ü, v or u:.
+    fn normalise(self, text: &str) -> String {
+        match self {
+            Self::Pinyin => normalise_pinyin(text),
+            Self::Jyutping => text.to_string(),
+        }
+    }
+    /// The tones of the romanization, as stored in the index.
+    fn tones(self) -> &'static [&'static str] {
+        match self {
+            // 5 is the neutral tone
+            Self::Pinyin => &["1", "2", "3", "4", "5"],
+            Self::Jyutping => &["1", "2", "3", "4", "5", "6"],
+        }
+    }
+    /// The tone which some sources write without a number, if any.
+    fn unnumbered_tone(self) -> Option<&'static str> {
+        match self {
+            Self::Pinyin => Some("5"),
+            Self::Jyutping => None,
+        }
+    }
+    /// Convert a tone number to the form stored in the index.
+    fn normalise_tone(self, tone: &str) -> String {
+        let tone = match (self, tone) {
+            // the neutral tone is sometimes written as 0
+            (Self::Pinyin, "0") => "5",
+            // the entering tones (on syllables ending in p, t or k) are sometimes
+            // numbered separately
+            (Self::Jyutping, "7") => "1",
+            (Self::Jyutping, "8") => "3",
+            (Self::Jyutping, "9") => "6",
+            (_, tone) => tone,
+        };
+        tone.to_string()
+    }
 }
 
-#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+#[derive(Eq, PartialEq, Hash, Clone, Debug, Serialize, Deserialize)]
 struct Syllable {
     sound: String,
     tone: String,
 }
 
-#[derive(Hash, Debug)]
+#[derive(Hash, Debug, Serialize, Deserialize)]
 struct Characters {
-    simplified: String,
-    traditional: String,
+    traditional: Box<str>,
+    // only stored if different from the traditional characters
+    simplified: Option<Box<str>>,
+}
+
+impl Characters {
+    /// Pair up the traditional and simplified characters at each position.
+    fn pairs(&self) -> Vec<(char, char)> {
+        let mut simplified = self.simplified.as_deref().unwrap_or(&self.traditional).chars();
+        self.traditional
+            .chars()
+            .map(|t| (t, simplified.next().unwrap_or(t)))
+            .collect()
+    }
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+struct WordStats {
+    frequency: i32,
+    // ids of the dictionaries with an entry for the word
+    dictionaries: Vec<i32>,
+}
+
+/// How well a word matches a query. Scores are ordered from worst to best, so
+/// that the best result is the greatest.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+pub struct Score {
+    /// Whether the pronunciation only matches the query when sounds are merged.
+    /// These matches come after all others.
+    pub fuzzy: bool,
+    /// Whether the query matches the whole word (or the whole sense for English
+    /// searches), rather than only the start of it.
+    pub exact: bool,
+    /// The number of characters or syllables in the word (or terms in the sense
+    /// for English searches). Shorter words are better.
+    pub length: usize,
+    pub frequency: i32,
+    /// The number of dictionaries containing the word.
+    pub dictionaries: usize,
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .fuzzy
+            .cmp(&self.fuzzy)
+            .then(self.exact.cmp(&other.exact))
+            .then(other.length.cmp(&self.length))
+            .then(self.frequency.cmp(&other.frequency))
+            .then(self.dictionaries.cmp(&other.dictionaries))
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A word matching a search query.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+pub struct SearchResult {
+    pub id: Id,
+    pub score: Score,
+}
+
+/// The most results returned at once.
+pub const MAX_PAGE_SIZE: usize = 100;
+/// Only the best results are ranked, so results past this many cannot be paged to.
+pub const MAX_RESULTS: usize = 1000;
+
+/// Which results to return from a search.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Page {
+    pub offset: usize,
+    /// At most `MAX_PAGE_SIZE`.
+    pub limit: usize,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: 20,
+        }
+    }
+}
+
+/// How the characters of a query are compared with the characters of words.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct MatchOptions {
+    /// Only match the characters of the query, not their variants (e.g. 裡 for 裏).
+    pub strict: bool,
+    /// Which form of words the query is compared with.
+    pub script: Script,
+}
+
+impl MatchOptions {
+    /// Only the characters of the query, in either form.
+    pub(crate) const STRICT: Self = Self {
+        strict: true,
+        script: Script::Any,
+    };
+}
+
+/// The forms of words which a character query may match.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Script {
+    /// Match the traditional or the simplified form of each character.
+    #[default]
+    Any,
+    Traditional,
+    Simplified,
 }
 
+/// A page of search results, best first.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct SearchPage<T = SearchResult> {
+    /// The number of matches, including those in other pages.
+    pub total: usize,
+    pub results: Vec<T>,
+}
+
+/// A word matching a query whose kind was detected automatically, with the kinds of
+/// search which found it.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct LabelledResult {
+    pub id: Id,
+    pub score: Score,
+    pub kinds: Vec<QueryKind>,
+}
+
+// Posting lists (the ids containing a syllable, character or term) are sorted vectors.
+#[derive(Serialize, Deserialize)]
 pub struct DictSearch {
-    pronunciation_reverse_map: HashMap<(PronunciationType, Syllable), HashSet<Id>>,
-    pronunciation_map: HashMap<(PronunciationType, Id), Vec<Syllable>>,
+    // Each distinct syllable is stored once, and pronunciations refer to it by index.
+    syllable_ids: HashMap<(PronunciationType, Syllable), InternId>,
+    syllables: Vec<Syllable>,
+    // the pronunciations containing each syllable
+    syllable_postings: Vec<Vec<Id>>,
+    pronunciation_map: HashMap<(PronunciationType, Id), Box<[InternId]>>,
+    // the word each pronunciation belongs to, and the pronunciations of each word
+    pronunciation_words: HashMap<Id, Id>,
+    word_pronunciations: HashMap<Id, Vec<Id>>,
     // "character" here means a Chinese character (字)
-    character_reverse_map: HashMap<char, HashSet<Id>>,
+    // the words containing each character, with its positions in them
+    character_positions: HashMap<char, Vec<(Id, u16)>>,
     characters_map: HashMap<Id, Characters>,
+    // English terms in definitions (stored once each), and the terms of each sense of a word.
+    term_ids: HashMap<String, InternId>,
+    term_postings: Vec<Vec<Id>>,
+    definitions_map: HashMap<Id, Vec<Box<[InternId]>>>,
+    word_stats: HashMap<Id, WordStats>,
+    // characters and romanizations of words, for completing partial input
+    suggestions: Trie,
+    // the radical and strokes of each character, and the characters with each
+    // radical, ordered by residual strokes
+    stroke_info: HashMap<char, StrokeInfo>,
+    radical_map: HashMap<u8, Vec<(i8, char)>>,
+    // the direct components of each character, and the characters containing each
+    // component at any depth
+    decompositions: HashMap<char, Box<[char]>>,
+    component_map: HashMap<char, Vec<char>>,
+    // the variants of each character (e.g. 裡 for 裏), sorted
+    variants: HashMap<char, Vec<char>>,
 }
 
 impl DictSearch {
     pub fn new() -> Self {
         Self {
-            pronunciation_reverse_map: HashMap::new(),
+            syllable_ids: HashMap::new(),
+            syllables: Vec::new(),
+            syllable_postings: Vec::new(),
             pronunciation_map: HashMap::new(),
-            character_reverse_map: HashMap::new(),
+            pronunciation_words: HashMap::new(),
+            word_pronunciations: HashMap::new(),
+            character_positions: HashMap::new(),
             characters_map: HashMap::new(),
+            term_ids: HashMap::new(),
+            term_postings: Vec::new(),
+            definitions_map: HashMap::new(),
+            word_stats: HashMap::new(),
+            suggestions: Trie::new(),
+            stroke_info: HashMap::new(),
+            radical_map: HashMap::new(),
+            decompositions: HashMap::new(),
+            component_map: HashMap::new(),
+            variants: HashMap::new(),
         }
     }
     pub fn insert_pronunciation(
         &mut self,
         id: Id,
+        word_id: Id,
         content: &str,
         pronunciation_type: PronunciationType,
     ) {
-        // todo. some entries have no spaces in between syllables
-        let (_, tokens) = tokenise_pronunciation(content).unwrap();
+        let tokens = parse_pronunciation(content, pronunciation_type);
 
-        for token in &tokens {
-            self.pronunciation_reverse_map
+        let mut syllable_ids = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let syllables = &mut self.syllables;
+            let syllable_postings = &mut self.syllable_postings;
+            let syllable_id = *self
+                .syllable_ids
                 .entry((pronunciation_type, token.clone()))
-                .or_insert_with(|| HashSet::new())
-                .insert(id);
+                .or_insert_with(|| {
+                    syllables.push(token);
+                    syllable_postings.push(Vec::new());
+                    (syllables.len() - 1) as InternId
+                });
+            insert_posting(&mut self.syllable_postings[syllable_id as usize], id);
+            syllable_ids.push(syllable_id);
+        }
+        for key in &self.suggestion_keys(&syllable_ids) {
+            self.suggestions.insert(key, word_id);
+        }
+        self.pronunciation_map
+            .insert((pronunciation_type, id), syllable_ids.into_boxed_slice());
+        self.pronunciation_words.insert(id, word_id);
+        self.word_pronunciations.entry(word_id).or_default().push(id);
+    }
+    /// Remove a pronunciation from the index.
+    pub fn remove_pronunciation(&mut self, id: Id) {
+        let word_id = match self.pronunciation_words.remove(&id) {
+            Some(word_id) => word_id,
+            None => return,
+        };
+        let mut keys = Vec::new();
+        for &pronunciation_type in &[PronunciationType::Jyutping, PronunciationType::Pinyin] {
+            if let Some(syllable_ids) = self.pronunciation_map.remove(&(pronunciation_type, id)) {
+                for syllable_id in syllable_ids.iter() {
+                    remove_posting(&mut self.syllable_postings[*syllable_id as usize], id);
+                }
+                keys.extend(self.suggestion_keys(&syllable_ids).iter().cloned());
+            }
+        }
+        if let Some(pronunciations) = self.word_pronunciations.get_mut(&word_id) {
+            pronunciations.retain(|other| *other != id);
+            if pronunciations.is_empty() {
+                self.word_pronunciations.remove(&word_id);
+            }
+        }
+
+        // other pronunciations of the word may still complete the same keys
+        let mut remaining = Vec::new();
+        for other in self.word_pronunciations.get(&word_id).into_iter().flatten() {
+            for &pronunciation_type in &[PronunciationType::Jyutping, PronunciationType::Pinyin] {
+                if let Some(syllable_ids) = self.pronunciation_map.get(&(pronunciation_type, *other)) {
+                    remaining.extend(self.suggestion_keys(syllable_ids).iter().cloned());
+                }
+            }
+        }
+        for key in keys {
+            if !remaining.contains(&key) {
+                self.suggestions.remove(&key, word_id);
+            }
         }
-        self.pronunciation_map.insert((pronunciation_type, id), tokens);
+    }
+    /// Replace a pronunciation (e.g. after it is edited).
+    pub fn update_pronunciation(
+        &mut self,
+        id: Id,
+        word_id: Id,
+        content: &str,
+        pronunciation_type: PronunciationType,
+    ) {
+        self.remove_pronunciation(id);
+        self.insert_pronunciation(id, word_id, content, pronunciation_type);
+    }
+    /// The keys completing a pronunciation, typed with or without tones.
+    fn suggestion_keys(&self, syllable_ids: &[InternId]) -> [String; 2] {
+        let syllables = syllable_ids.iter().map(|syllable_id| &self.syllables[*syllable_id as usize]);
+        let toned = syllables.clone().map(|syllable| format!("{}{}", syllable.sound, syllable.tone)).collect();
+        let toneless = syllables.map(|syllable| &*syllable.sound).collect();
+        [toned, toneless]
     }
     pub fn insert_characters(&mut self, id: Id, trad: &str, simp: &str) {
-        for character in simp.chars() {
-            self.character_reverse_map
-                .entry(character.clone())
-                .or_insert_with(|| HashSet::new())
-                .insert(id);
-        }
-        for character in trad.chars() {
-            self.character_reverse_map
-                .entry(character.clone())
-                .or_insert_with(|| HashSet::new())
-                .insert(id);
-        }
-        self.characters_map.insert(
-            id,
-            Characters {
-                simplified: simp.to_string(),
-                traditional: trad.to_string(),
-            },
-        );
+        self.suggestions.insert(simp, id);
+        self.suggestions.insert(trad, id);
+        let characters = Characters {
+            traditional: trad.into(),
+            simplified: if simp == trad { None } else { Some(simp.into()) },
+        };
+        self.insert_positions(id, &characters);
+        self.characters_map.insert(id, characters);
+    }
+    /// Remove a word's characters from the index.
+    pub fn remove_characters(&mut self, id: Id) {
+        let characters = match self.characters_map.remove(&id) {
+            Some(characters) => characters,
+            None => return,
+        };
+        self.remove_positions(id, &characters);
+        let simplified = characters.simplified.as_deref().unwrap_or(&characters.traditional);
+        self.suggestions.remove(simplified, id);
+        self.suggestions.remove(&characters.traditional, id);
+    }
+    /// Replace a word's characters (e.g. after they are edited).
+    pub fn update_characters(&mut self, id: Id, trad: &str, simp: &str) {
+        self.remove_characters(id);
+        self.insert_characters(id, trad, simp);
+    }
+    /// Set how common a word is, for ranking results.
+    pub fn set_frequency(&mut self, id: Id, frequency: i32) {
+        self.word_stats.entry(id).or_default().frequency = frequency;
     }
+    /// Index the definitions of a word's entry in a dictionary. The senses are added
+    /// to any previously inserted for the word, as a word may have several entries.
+    pub fn insert_definitions(&mut self, id: Id, dictionary_id: i32, definitions: &str) {
+        let dictionaries = &mut self.word_stats.entry(id).or_default().dictionaries;
+        if !dictionaries.contains(&dictionary_id) {
+            dictionaries.push(dictionary_id);
+        }
+
+        let senses = self.definitions_map.entry(id).or_default();
+        for sense in split_senses(definitions) {
+            let terms = tokenise_english(sense);
+            if terms.is_empty() {
+                continue;
+            }
+            let mut term_ids = Vec::with_capacity(terms.len());
+            for term in terms {
+                let term_postings = &mut self.term_postings;
+                let term_id = *self.term_ids.entry(term).or_insert_with(|| {
+                    term_postings.push(Vec::new());
+                    (term_postings.len() - 1) as InternId
+                });
+                insert_posting(&mut self.term_postings[term_id as usize], id);
+                term_ids.push(term_id);
+            }
+            senses.push(term_ids.into_boxed_slice());
+        }
+    }
+    /// Remove all the definitions of a word, and the dictionaries it is in. To update
+    /// them, insert the definitions of each of the word's entries again afterwards.
+    pub fn remove_definitions(&mut self, id: Id) {
+        if let Some(stats) = self.word_stats.get_mut(&id) {
+            stats.dictionaries.clear();
+        }
+        for sense in self.definitions_map.remove(&id).into_iter().flatten() {
+            for term_id in sense.iter() {
+                remove_posting(&mut self.term_postings[*term_id as usize], id);
+            }
+        }
+    }
+    /// Remove a word from the index, with its characters, pronunciations and definitions.
+    pub fn remove_word(&mut self, id: Id) {
+        self.remove_characters(id);
+        self.remove_definitions(id);
+        for pronunciation_id in self.word_pronunciations.get(&id).cloned().unwrap_or_default() {
+            self.remove_pronunciation(pronunciation_id);
+        }
+        self.word_stats.remove(&id);
+    }
+    /// Rank the words to suggest for each prefix by frequency. This must be called
+    /// after inserting words (and their frequencies), before `suggest`, and again
+    /// after later changes for them to be fully reflected in suggestions.
+    pub fn rank_suggestions(&mut self) {
+        let word_stats = &self.word_stats;
+        let frequency = |id: &Id| word_stats.get(id).map_or(0, |stats| stats.frequency);
+        self.suggestions
+            .rank(MAX_SUGGESTIONS, |a, b| frequency(b).cmp(&frequency(a)).then(a.cmp(b)));
+    }
+    /// Complete partially typed characters or romanization (e.g. "垃" or "hou2 j"),
+    /// returning at most `limit` word ids, most frequent first. Spaces are ignored,
+    /// and syllables typed without a tone match any tone.
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<Id> {
+        // only pinyin is rewritten, so that e.g. jyutping is not split as pinyin
+        let prefix = if has_tone_marks(prefix) || detect_query(prefix).contains(&QueryKind::Pinyin) {
+            normalise_pinyin(prefix)
+        } else {
+            prefix.to_lowercase()
+        };
+        let prefix: String = prefix
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect();
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+        let ids = self.suggestions.complete(&prefix);
+        if !ids.is_empty() || !prefix.contains(|c: char| c.is_ascii_digit()) {
+            return ids[..ids.len().min(limit)].to_vec();
+        }
+        // Only some syllables have tones (e.g. "laap saap3"), which no key has, so
+        // complete the prefix without tones and keep the words with the tones given.
+        // These are found among the suggestions for the prefix without tones only.
+        let toneless: String = prefix.chars().filter(|c| !c.is_ascii_digit()).collect();
+        self.suggestions
+            .complete(&toneless)
+            .iter()
+            .filter(|id| self.has_tones_of(**id, &prefix))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+    /// Check whether a pronunciation of a word starts with a prefix, where the
+    /// syllables of the prefix without tones may have any tone.
+    fn has_tones_of(&self, id: Id, prefix: &str) -> bool {
+        let mut pronunciation_ids = self.word_pronunciations.get(&id).into_iter().flatten();
+        pronunciation_ids.any(|pronunciation_id| {
+            [PronunciationType::Jyutping, PronunciationType::Pinyin].iter().any(|pronunciation_type| {
+                self.pronunciation_map
+                    .get(&(*pronunciation_type, *pronunciation_id))
+                    .is_some_and(|syllable_ids| {
+                        let [toned, _] = self.suggestion_keys(syllable_ids);
+                        let mut key = toned.chars();
+                        prefix.chars().all(|c| key.by_ref().find(|k| *k == c || !k.is_ascii_digit()) == Some(c))
+                    })
+            })
+        })
+    }
+    fn score(&self, id: Id, exact: bool, length: usize) -> Score {
+        let (frequency, dictionaries) = match self.word_stats.get(&id) {
+            Some(stats) => (stats.frequency, stats.dictionaries.len()),
+            None => (0, 0),
+        };
+        Score {
+            fuzzy: false,
+            exact,
+            length,
+            frequency,
+            dictionaries,
+        }
+    }
+    /// Search for words with a pronunciation matching the query, best matches first.
+    ///
+    /// `?` in the query matches exactly one syllable and `*` matches zero or more syllables.
+    /// A query without `*` matches the start of a pronunciation (as if it ended with `*`).
     pub fn search_pronunciation(
         &self,
         query: &str,
         pronunciation_type: PronunciationType,
-    ) -> Result<Vec<Id>, SearchError> {
+        page: Page,
+    ) -> Result<SearchPage, SearchError> {
+        self.search_pronunciation_fuzzy(query, pronunciation_type, &[], page)
+    }
+    /// Search for words with a pronunciation matching the query, treating the sounds
+    /// in each of `mergers` as the same (see `Merger::defaults` for the common mergers).
+    /// Words matching without merging any sounds come first.
+    pub fn search_pronunciation_fuzzy(
+        &self,
+        query: &str,
+        pronunciation_type: PronunciationType,
+        mergers: &[Merger],
+        page: Page,
+    ) -> Result<SearchPage, SearchError> {
+        Ok(rank(self.pronunciation_matches(query, pronunciation_type, mergers)?, page))
+    }
+    fn pronunciation_matches(
+        &self,
+        query: &str,
+        pronunciation_type: PronunciationType,
+        mergers: &[Merger],
+    ) -> Result<HashMap<Id, Score>, SearchError> {
+        let query = pronunciation_type.normalise(query);
         let s = tokenise_pronunciation_query(&query);
         let query_tokens = s.map_err(|_| SearchError::InvalidInput)?.1;
 
-        let mut searches = Vec::new();
-
-        // Apply a broad filter by filtering by the first
-        // non-wildcard token in the search query.
-        let mut broad_filter = None;
-        for token in &query_tokens {
-            if let QueryToken::Pronunciation { sound, tone } = token {
-                broad_filter = Some((sound, tone));
-                break;
+        let mut results = HashMap::new();
+        // search each way of splitting syllables written without spaces
+        for query_tokens in segment_query(query_tokens, pronunciation_type) {
+            self.match_pronunciation(&anchor_query(query_tokens), pronunciation_type, mergers, &mut results)?;
+        }
+        Ok(results)
+    }
+    /// Add the words with a pronunciation matching the (anchored) query tokens to `results`.
+    fn match_pronunciation(
+        &self,
+        query_tokens: &[QueryToken],
+        pronunciation_type: PronunciationType,
+        mergers: &[Merger],
+        results: &mut HashMap<Id, Score>,
+    ) -> Result<(), SearchError> {
+        // The sounds which each sound in the query matches.
+        let mut merged_sounds = HashMap::new();
+        for token in query_tokens {
+            if let QueryToken::Pronunciation { sound, .. } = token {
+                merged_sounds.insert(sound, fuzzy_sounds(pronunciation_type, sound, mergers));
             }
         }
 
-        // if tone is empty
-        if let Some((sound, tone)) = broad_filter {
-            // if tone unspecified
-            if tone == "" {
-                // consider all tones
-                for i in 1..7 {
-                    searches.push(Syllable {
-                        sound: sound.clone(),
-                        tone: i.to_string(),
-                    });
+        // Apply a broad filter using the non-wildcard token which
+        // matches the fewest pronunciations.
+        let mut broad_filter: Option<Vec<&Vec<Id>>> = None;
+        for token in query_tokens {
+            if let QueryToken::Pronunciation { sound, tone } = token {
+                let postings = self.postings_for_syllable(&merged_sounds[sound], tone, pronunciation_type);
+                let count = |p: &Vec<&Vec<Id>>| p.iter().map(|ids| ids.len()).sum::<usize>();
+                if broad_filter.as_ref().is_none_or(|best| count(&postings) < count(best)) {
+                    broad_filter = Some(postings);
                 }
-            } else {
-                searches.push(Syllable {
-                    sound: sound.clone(),
-                    tone: tone.clone(),
-                });
             }
-        } else {
-            return Err(SearchError::InvalidInput);
         }
+        let broad_filter = broad_filter.ok_or(SearchError::InvalidInput)?;
+
+        for pronunciation_ids in broad_filter {
+            for id in pronunciation_ids {
+                let candidate = self.pronunciation_map.get(&(pronunciation_type, *id)).unwrap();
 
-        let mut results = Vec::new();
-        // broad phase filter according to the first non-wildcard syllable in the query
-        for search in searches {
-            if let Some(pronunciation_ids) = self
-                .pronunciation_reverse_map
-                .get(&(pronunciation_type, search))
-            {
-                for id in pronunciation_ids {
-                    let candidate = self.pronunciation_map.get(&(pronunciation_type, *id)).unwrap();
-                    // is the candidate valid?
-                    let mut good = true;
-
-                    for (i, token) in query_tokens.iter().enumerate() {
-                        if let QueryToken::Pronunciation { sound, tone } = token {
-                            // fetch token from candidate.
-                            let candidate_syllable = match candidate.get(i) {
-                                Some(t) => t,
-                                None => {
-                                    good = false;
-                                    break;
-                                }
+                // check if syllable matches, either exactly or after merging sounds.
+                let matches = |fuzzy: bool| {
+                    glob_match(query_tokens, candidate, |token, syllable_id| match token {
+                        QueryToken::Pronunciation { sound, tone } => {
+                            let syllable = &self.syllables[*syllable_id as usize];
+                            let sound_matches = if fuzzy {
+                                merged_sounds[sound].contains(&syllable.sound)
+                            } else {
+                                &syllable.sound == sound
                             };
-                            // check if syllable matches.
-                            if &candidate_syllable.sound != sound
-                                || (tone != "" && &candidate_syllable.tone != tone)
-                            {
-                                good = false;
-                                break;
-                            }
+                            sound_matches && tone_matches(tone, &syllable.tone)
                         }
-                        if let QueryToken::WildcardSingle = token {
-                            // check that there is actually a syllable.
-                            if let None = candidate.get(i) {
-                                good = false;
-                                break;
-                            }
+                        _ => false,
+                    })
+                };
+                let fuzzy = if matches(false) {
+                    false
+                } else if !mergers.is_empty() && matches(true) {
+                    true
+                } else {
+                    continue;
+                };
+                let word_id = self.pronunciation_words[id];
+                let exact = candidate.len() == fixed_length(query_tokens);
+                let score = Score {
+                    fuzzy,
+                    ..self.score(word_id, exact, candidate.len())
+                };
+                insert_best(results, word_id, score);
+            }
+        }
+        Ok(())
+    }
+
+    /// The ids of the pronunciations containing a syllable with one of `sounds`
+    /// and a tone matching `tone`.
+    fn postings_for_syllable(
+        &self,
+        sounds: &[String],
+        tone: &str,
+        pronunciation_type: PronunciationType,
+    ) -> Vec<&Vec<Id>> {
+        // syllables with an unknown tone match any tone
+        let mut tones = vec![""];
+        // if tone unspecified
+        if tone.is_empty() {
+            // consider all tones
+            tones.extend(pronunciation_type.tones());
+        } else {
+            tones.push(tone);
+        }
+        let mut postings = Vec::new();
+        for sound in sounds {
+            for tone in &tones {
+                let syllable = Syllable {
+                    sound: sound.clone(),
+                    tone: tone.to_string(),
+                };
+                if let Some(syllable_id) = self.syllable_ids.get(&(pronunciation_type, syllable)) {
+                    postings.push(&self.syllable_postings[*syllable_id as usize]);
+                }
+            }
+        }
+        postings
+    }
+
+    /// Search for words matching a query where each position is a character, a syllable
+    /// (with or without a tone) or a wildcard, e.g. "垃 saap3 ?". Syllables are matched
+    /// against the pronunciation of the character at the same position.
+    ///
+    /// Wildcards are the same as in `search_characters` and `search_pronunciation`.
+    pub fn search_mixed(
+        &self,
+        query: &str,
+        pronunciation_type: PronunciationType,
+        page: Page,
+    ) -> Result<SearchPage, SearchError> {
+        Ok(rank(self.mixed_matches(query, pronunciation_type)?, page))
+    }
+    fn mixed_matches(
+        &self,
+        query: &str,
+        pronunciation_type: PronunciationType,
+    ) -> Result<HashMap<Id, Score>, SearchError> {
+        let query = pronunciation_type.normalise(query);
+        let s = tokenise_mixed_query(&query);
+        let query_tokens = s.map_err(|_| SearchError::InvalidInput)?.1;
+
+        let mut results = HashMap::new();
+        // search each way of splitting syllables written without spaces
+        for query_tokens in segment_query(query_tokens, pronunciation_type) {
+            self.match_mixed(&anchor_query(query_tokens), pronunciation_type, &mut results)?;
+        }
+        Ok(results)
+    }
+    /// Add the words matching the (anchored) mixed query tokens to `results`.
+    fn match_mixed(
+        &self,
+        query_tokens: &[QueryToken],
+        pronunciation_type: PronunciationType,
+        results: &mut HashMap<Id, Score>,
+    ) -> Result<(), SearchError> {
+        // Apply a broad filter using the least common character or syllable.
+        let mut broad_filter: Option<HashSet<Id>> = None;
+        for token in query_tokens {
+            let word_ids: HashSet<Id> = match token {
+                QueryToken::Character(c) => self.words_with_character(*c, MatchOptions::STRICT).into_iter().collect(),
+                QueryToken::Pronunciation { sound, tone } => self
+                    .postings_for_syllable(std::slice::from_ref(sound), tone, pronunciation_type)
+                    .into_iter()
+                    .flatten()
+                    .map(|id| self.pronunciation_words[id])
+                    .collect(),
+                _ => continue,
+            };
+            if broad_filter.as_ref().is_none_or(|best| word_ids.len() < best.len()) {
+                broad_filter = Some(word_ids);
+            }
+        }
+        let broad_filter = broad_filter.ok_or(SearchError::InvalidInput)?;
+
+        for id in broad_filter {
+            let characters = match self.characters_map.get(&id) {
+                Some(candidate) => candidate.pairs(),
+                None => continue,
+            };
+
+            // the pronunciations with a syllable for each character
+            let mut alignments: Vec<Option<&[InternId]>> = self
+                .word_pronunciations
+                .get(&id)
+                .into_iter()
+                .flatten()
+                .filter_map(|pronunciation_id| self.pronunciation_map.get(&(pronunciation_type, *pronunciation_id)))
+                .filter(|syllables| syllables.len() == characters.len())
+                .map(|syllables| Some(&**syllables))
+                .collect();
+            // characters may still match a word without a usable pronunciation
+            if alignments.is_empty() {
+                alignments.push(None);
+            }
+
+            for syllables in alignments {
+                let positions: Vec<_> = (0..characters.len())
+                    .map(|i| (characters[i], syllables.map(|syllables| &self.syllables[syllables[i] as usize])))
+                    .collect();
+                let valid = glob_match(query_tokens, &positions, |token, ((trad_char, simp_char), syllable)| {
+                    match (token, syllable) {
+                        (QueryToken::Character(query_char), _) => query_char == trad_char || query_char == simp_char,
+                        (QueryToken::Pronunciation { sound, tone }, Some(syllable)) => {
+                            &syllable.sound == sound && tone_matches(tone, &syllable.tone)
                         }
+                        _ => false,
                     }
-                    if good {
-                        results.push(*id);
-                    }
+                });
+                if valid {
+                    let length = characters.len();
+                    let exact = length == fixed_length(query_tokens);
+                    insert_best(results, id, self.score(id, exact, length));
+                    break;
                 }
             }
         }
-        Ok(results)
+        Ok(())
+    }
+
+    /// Search for words matching the query as each kind of search it could be meant for
+    /// (see `detect_query`), best matches first. Words found by several searches are
+    /// given once, with their best score.
+    pub fn search_auto(&self, query: &str, page: Page) -> Result<SearchPage<LabelledResult>, SearchError> {
+        let kinds = detect_query(query);
+        if kinds.is_empty() {
+            return Err(SearchError::InvalidInput);
+        }
+
+        let mut results: HashMap<Id, LabelledResult> = HashMap::new();
+        for kind in kinds {
+            let found = match kind {
+                QueryKind::Characters => self.character_matches(query, MatchOptions::default()),
+                QueryKind::Jyutping => self.pronunciation_matches(query, PronunciationType::Jyutping, &[]),
+                QueryKind::Pinyin => self.pronunciation_matches(query, PronunciationType::Pinyin, &[]),
+                QueryKind::English => self.definition_matches(query),
+                QueryKind::Mixed => self.mixed_matches(query, PronunciationType::Jyutping).and_then(|mut found| {
+                    for (id, score) in self.mixed_matches(query, PronunciationType::Pinyin)? {
+                        insert_best(&mut found, id, score);
+                    }
+                    Ok(found)
+                }),
+            };
+            // e.g. an English query of only stop words
+            let found = match found {
+                Ok(found) => found,
+                Err(SearchError::InvalidInput) => continue,
+            };
+            for (id, score) in found {
+                let result = results.entry(id).or_insert(LabelledResult {
+                    id,
+                    score,
+                    kinds: Vec::new(),
+                });
+                result.score = std::cmp::max(result.score, score);
+                result.kinds.push(kind);
+            }
+        }
+
+        let results = results.into_values().collect();
+        Ok(paginate(results, page, |a, b| b.score.cmp(&a.score).then(a.id.cmp(&b.id))))
     }
 
-    pub fn search_characters(&self, query: &str) -> Result<Vec<Id>, SearchError> {
+    /// Search for words with characters matching the query, best matches first.
+    ///
+    /// `?` in the query matches exactly one character and `*` matches zero or more characters.
+    /// A query without `*` matches the start of a word (as if it ended with `*`).
+    pub fn search_characters(&self, query: &str, page: Page) -> Result<SearchPage, SearchError> {
+        self.search_characters_with(query, MatchOptions::default(), page)
+    }
+    /// Search for words with characters matching the query, choosing whether variants
+    /// of the characters in the query match.
+    pub fn search_characters_with(
+        &self,
+        query: &str,
+        options: MatchOptions,
+        page: Page,
+    ) -> Result<SearchPage, SearchError> {
+        Ok(rank(self.character_matches(query, options)?, page))
+    }
+    /// Search for words starting with a character, e.g. one found by its radical.
+    /// Wildcards are not allowed.
+    pub fn search_starting_with(&self, character: char, page: Page) -> Result<SearchPage, SearchError> {
+        if character == '?' || character == '*' {
+            return Err(SearchError::InvalidInput);
+        }
+        self.search_characters(&format!("{}*", character), page)
+    }
+    fn character_matches(&self, query: &str, options: MatchOptions) -> Result<HashMap<Id, Score>, SearchError> {
         let s = tokenise_characters_query(&query);
-        let query_tokens = s.map_err(|_| SearchError::InvalidInput)?.1;
+        let query_tokens = anchor_query(s.map_err(|_| SearchError::InvalidInput)?.1);
 
-        let mut broad_filter_option = None;
+        // Apply a broad filter using the least common character in the query.
+        let mut broad_filter: Option<Cow<[(Id, u16)]>> = None;
         for token in &query_tokens {
             if let QueryToken::Character(c) = token {
-                broad_filter_option = Some(c);
-                break;
+                let positions = self.positions_of(*c, options);
+                if positions.is_empty() {
+                    // no word has this character
+                    return Ok(HashMap::new());
+                }
+                if broad_filter.as_ref().is_none_or(|best| positions.len() < best.len()) {
+                    broad_filter = Some(positions);
+                }
             };
         }
 
-        let broad_filter = broad_filter_option.ok_or(SearchError::InvalidInput)?;
-        let mut results = Vec::new();
+        let broad_filter = broad_filter.ok_or(SearchError::InvalidInput)?;
+        let mut results = HashMap::new();
 
-        if let Some(word_ids) = self.character_reverse_map.get(broad_filter) {
-            for id in word_ids {
-                let candidate = self.characters_map.get(&id).unwrap();
-                let mut valid = true;
+        // the positions of each word are together
+        let word_ids = broad_filter.iter().map(|(id, _)| id);
+        let mut previous = None;
+        for id in word_ids.filter(|id| previous.replace(**id) != Some(**id)) {
+            let characters = self.characters_map.get(&id).unwrap().pairs();
 
-                for (i, token) in query_tokens.iter().enumerate() {
-                    if let QueryToken::Character(query_char) = token {
-                        // allow simplified or traditional variants for each character.
-                        let trad_char = match candidate.traditional.chars().nth(i) {
-                            Some(t) => t,
-                            None => {
-                                valid = false;
-                                break;
-                            }
-                        };
-                        let simp_char = match candidate.simplified.chars().nth(i) {
-                            Some(t) => t,
-                            None => {
-                                valid = false;
-                                break;
-                            }
-                        };
-                        if query_char != &trad_char && query_char != &simp_char {
-                            valid = false;
-                            break;
-                        }
-                    } else if let QueryToken::WildcardSingle = token {
-                        // check that there is actually a syllable.
-                        if candidate.traditional.chars().nth(i).is_none()
-                            && candidate.simplified.chars().nth(i).is_none()
-                        {
-                            valid = false;
-                            break;
-                        }
-                    }
-                }
+            let valid = glob_match(&query_tokens, &characters, |token, pair| match token {
+                // allow simplified or traditional forms (and variants) for each character.
+                QueryToken::Character(query_char) => self.characters_match(*query_char, *pair, options),
+                _ => false,
+            });
 
-                if valid {
-                    results.push(*id);
+            if valid {
+                let length = characters.len();
+                let exact = length == fixed_length(&query_tokens);
+                results.insert(*id, self.score(*id, exact, length));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Search for words with an English definition containing every term in the query.
+    /// Words with a sense consisting of only the query terms come first, followed by
+    /// words whose senses contain fewer other terms.
+    pub fn search_definitions(&self, query: &str, page: Page) -> Result<SearchPage, SearchError> {
+        Ok(rank(self.definition_matches(query)?, page))
+    }
+    fn definition_matches(&self, query: &str) -> Result<HashMap<Id, Score>, SearchError> {
+        let query_terms = tokenise_english(query);
+        if query_terms.is_empty() {
+            return Err(SearchError::InvalidInput);
+        }
+
+        let mut query_ids = Vec::with_capacity(query_terms.len());
+        for term in &query_terms {
+            match self.term_ids.get(term) {
+                Some(term_id) => query_ids.push(*term_id),
+                None => return Ok(HashMap::new()),
+            }
+        }
+        // Use the least common term as a broad filter.
+        let mut postings: Vec<&Vec<Id>> = query_ids
+            .iter()
+            .map(|term_id| &self.term_postings[*term_id as usize])
+            .collect();
+        postings.sort_by_key(|ids| ids.len());
+
+        let mut results = HashMap::new();
+        for id in postings[0] {
+            if !postings[1..].iter().all(|ids| ids.binary_search(id).is_ok()) {
+                continue;
+            }
+            let senses = self.definitions_map.get(id).unwrap();
+            // terms split across several senses are the weakest matches
+            let mut best = self.score(*id, false, usize::MAX);
+            for sense in senses {
+                if !query_ids.iter().all(|term_id| sense.contains(term_id)) {
+                    continue;
                 }
+                let whole = query_ids.len() == sense.len();
+                best = std::cmp::max(best, self.score(*id, whole, sense.len()));
             }
+            results.insert(*id, best);
         }
         Ok(results)
     }
 }
 
+/// Add an id to a sorted posting list, if it is not already there.
+fn insert_posting(postings: &mut Vec<Id>, id: Id) {
+    if let Err(i) = postings.binary_search(&id) {
+        postings.insert(i, id);
+    }
+}
+
+/// Remove an id from a sorted posting list.
+fn remove_posting(postings: &mut Vec<Id>, id: Id) {
+    if let Ok(i) = postings.binary_search(&id) {
+        postings.remove(i);
+    }
+}
+
 /// Load database data into the search index.
-pub fn load_search(search: &mut DictSearch, connection: &DbConnection) {
+pub fn load_search(search: &mut DictSearch, connection: &DbConnection) -> diesel::QueryResult<()> {
     use crate::diesel::prelude::*;
-    use crate::schema::{word::dsl::*, word_pronunciation::dsl::*};
+    use crate::schema::{character_decomposition, character_info, character_variant, word, word_entry, word_pronunciation};
 
-    let results = word_pronunciation
-        .load::<WordPronunciation>(connection)
-        .unwrap();
-    let words = word.load::<Word>(connection).unwrap();
+    // in id order, so that each id is added to the end of the sorted posting lists
+    let results = word_pronunciation::table
+        .inner_join(word_entry::table)
+        .select((word_pronunciation::all_columns, word_entry::word_id))
+        .order(word_pronunciation::pronunciation_id)
+        .load::<(WordPronunciation, Id)>(connection)?;
+    let words = word::table.order(word::word_id).load::<Word>(connection)?;
+    let entries = word_entry::table.order(word_entry::word_id).load::<WordEntry>(connection)?;
+    let character_infos = character_info::table.load::<CharacterInfo>(connection)?;
+    let decompositions = character_decomposition::table
+        .load::<CharacterDecomposition>(connection)?;
+    let variants = character_variant::table.load::<CharacterVariant>(connection)?;
 
-    for result in results {
+    for (result, word_id) in results {
         search.insert_pronunciation(
             result.pronunciation_id,
+            word_id,
             &result.pronunciation,
             PronunciationType::from_integer(result.pronunciation_type).unwrap(),
         );
@@ -292,9 +1017,226 @@ pub fn load_search(search: &mut DictSearch, connection: &DbConnection) {
             &word_result.traditional,
             &word_result.simplified,
         );
+        search.set_frequency(word_result.word_id, word_result.frequency);
+    }
+    for entry in entries {
+        search.insert_definitions(entry.word_id, entry.dictionary_id, &entry.definitions);
+    }
+    for info in character_infos {
+        if let Some(character) = info.character.chars().next() {
+            search.insert_stroke_info(
+                character,
+                StrokeInfo {
+                    radical: info.radical as u8,
+                    residual_strokes: info.residual_strokes as i8,
+                    total_strokes: info.total_strokes as u8,
+                },
+            );
+        }
+    }
+    for decomposition in decompositions {
+        if let Some(character) = decomposition.character.chars().next() {
+            search.insert_decomposition(character, &decomposition.decomposition);
+        }
+    }
+    search.index_components();
+    for variant in variants {
+        if let (Some(character), Some(other)) = (variant.character.chars().next(), variant.variant.chars().next()) {
+            search.insert_variant(character, other);
+        }
+    }
+    search.rank_suggestions();
+    Ok(())
+}
+
+/// Check whether the tone of a syllable in the index matches a tone in a query.
+/// An unspecified tone in the query, or an unknown tone in the index, matches any tone.
+fn tone_matches(query_tone: &str, tone: &str) -> bool {
+    query_tone.is_empty() || tone.is_empty() || query_tone == tone
+}
+
+/// Keep the best score for each word.
+fn insert_best(results: &mut HashMap<Id, Score>, id: Id, score: Score) {
+    let best = results.entry(id).or_insert(score);
+    if score > *best {
+        *best = score;
+    }
+}
+
+/// Sort results from best to worst, returning a page of them. Ties are ordered by id
+/// so that results are stable.
+fn rank(results: HashMap<Id, Score>, page: Page) -> SearchPage {
+    let results = results
+        .into_iter()
+        .map(|(id, score)| SearchResult { id, score })
+        .collect();
+    paginate(results, page, |a, b| b.score.cmp(&a.score).then(a.id.cmp(&b.id)))
+}
+
+/// Return a page of results, ordered by `order`. Only the results up to the end of
+/// the page (and at most `MAX_RESULTS`) are sorted.
+fn paginate<T>(mut results: Vec<T>, page: Page, order: impl Fn(&T, &T) -> Ordering) -> SearchPage<T> {
+    let total = results.len();
+    let end = page
+        .offset
+        .saturating_add(page.limit.min(MAX_PAGE_SIZE))
+        .min(MAX_RESULTS)
+        .min(total);
+    if end < total && end > 0 {
+        // move the best `end` results to the front
+        results.select_nth_unstable_by(end - 1, |a, b| order(a, b));
+    }
+    results.truncate(end);
+    results.sort_by(|a, b| order(a, b));
+    let start = page.offset.min(end);
+    SearchPage {
+        total,
+        results: results.split_off(start),
     }
 }
 
+/// Split a pronunciation into syllables, in the form stored in the index.
+fn parse_pronunciation(content: &str, pronunciation_type: PronunciationType) -> Vec<Syllable> {
+    let content = pronunciation_type.normalise(content);
+    let (_, mut tokens) = tokenise_pronunciation(&content).unwrap();
+
+    // In a pronunciation with tone numbers, a syllable without one has the
+    // unnumbered (e.g. neutral) tone. Otherwise its tone is unknown.
+    let numbered = tokens.iter().any(|token| !token.tone.is_empty());
+    for token in &mut tokens {
+        token.tone = match pronunciation_type.unnumbered_tone() {
+            Some(tone) if numbered && token.tone.is_empty() => tone.to_string(),
+            _ => pronunciation_type.normalise_tone(&token.tone),
+        };
+    }
+    // some entries have no spaces in between syllables
+    segment_pronunciation(tokens, pronunciation_type)
+}
+
+/// Split sounds written without spaces (e.g. "nihao") into syllables, with the tone
+/// on the last syllable. Sounds which cannot be split are left as they are.
+fn segment_pronunciation(tokens: Vec<Syllable>, pronunciation_type: PronunciationType) -> Vec<Syllable> {
+    let mut syllables = Vec::new();
+    for token in &tokens {
+        match segment(pronunciation_type, &token.sound, 1).first() {
+            Some(sounds) => syllables.extend(
+                with_tone(sounds, &token.tone).map(|(sound, tone)| Syllable { sound, tone }),
+            ),
+            None => syllables.push(token.clone()),
+        }
+    }
+    syllables
+}
+
+/// Split sounds in a query written without spaces into syllables, and normalise tones.
+/// An ambiguous query (e.g. "xian" as "xian" or "xi an") gives several queries, one
+/// for each split.
+fn segment_query(query_tokens: Vec<QueryToken>, pronunciation_type: PronunciationType) -> Vec<Vec<QueryToken>> {
+    let mut queries = vec![Vec::new()];
+    for token in &query_tokens {
+        let mut alternatives: Vec<Vec<QueryToken>> = Vec::new();
+        if let QueryToken::Pronunciation { sound, tone } = token {
+            let tone = &pronunciation_type.normalise_tone(tone);
+            for sounds in segment(pronunciation_type, sound, MAX_SEGMENTATIONS) {
+                alternatives.push(
+                    with_tone(&sounds, tone)
+                        .map(|(sound, tone)| QueryToken::Pronunciation { sound, tone })
+                        .collect(),
+                );
+            }
+        }
+        if alternatives.is_empty() {
+            alternatives.push(vec![match token {
+                QueryToken::Pronunciation { sound, tone } => QueryToken::Pronunciation {
+                    sound: sound.clone(),
+                    tone: pronunciation_type.normalise_tone(tone),
+                },
+                token => token.clone(),
+            }]);
+        }
+
+        let mut next = Vec::new();
+        for query in &queries {
+            for alternative in &alternatives {
+                next.push(query.iter().chain(alternative).cloned().collect());
+            }
+        }
+        next.truncate(MAX_SEGMENTATIONS);
+        queries = next;
+    }
+    queries
+}
+
+/// Pair each sound with a tone. Only the last sound gets `tone`, as the tone of the
+/// other syllables is unknown.
+fn with_tone<'a>(sounds: &'a [&'a str], tone: &'a str) -> impl Iterator<Item = (String, String)> + 'a {
+    sounds.iter().enumerate().map(move |(i, sound)| {
+        let tone = if i + 1 == sounds.len() { tone } else { "" };
+        (sound.to_string(), tone.to_string())
+    })
+}
+
+/// Queries without a `*` wildcard match the start of words.
+fn anchor_query(mut query_tokens: Vec<QueryToken>) -> Vec<QueryToken> {
+    if !query_tokens.contains(&QueryToken::WildcardMulti) {
+        query_tokens.push(QueryToken::WildcardMulti);
+    }
+    query_tokens
+}
+
+/// The number of characters or syllables in a query, not counting `*` wildcards.
+fn fixed_length(query_tokens: &[QueryToken]) -> usize {
+    query_tokens
+        .iter()
+        .filter(|token| **token != QueryToken::WildcardMulti)
+        .count()
+}
+
+/// Check whether a query matches the whole of a candidate. `matches` compares a
+/// non-wildcard query token with an element of the candidate.
+///
+/// This is the usual greedy wildcard matching algorithm: when a mismatch happens,
+/// backtrack so that the most recent `*` consumes one more element.
+fn glob_match<T>(
+    query_tokens: &[QueryToken],
+    candidate: &[T],
+    matches: impl Fn(&QueryToken, &T) -> bool,
+) -> bool {
+    let (mut q, mut c) = (0, 0);
+    // the query position after the last `*`, and the candidate position it was matched at
+    let mut backtrack = None;
+
+    while c < candidate.len() {
+        match query_tokens.get(q) {
+            Some(QueryToken::WildcardMulti) => {
+                q += 1;
+                backtrack = Some((q, c));
+                continue;
+            }
+            Some(QueryToken::WildcardSingle) => {
+                q += 1;
+                c += 1;
+                continue;
+            }
+            Some(token) if matches(token, &candidate[c]) => {
+                q += 1;
+                c += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((star_q, star_c)) => {
+                q = star_q;
+                c = star_c + 1;
+                backtrack = Some((star_q, star_c + 1));
+            }
+            None => return false,
+        }
+    }
+    query_tokens[q..].iter().all(|token| *token == QueryToken::WildcardMulti)
+}
+
 fn tokenise_pronunciation(content: &str) -> IResult<&str, Vec<Syllable>> {
     separated_list_custom(
         // spaces and unknown characters
@@ -315,9 +1257,10 @@ fn tokenise_pronunciation(content: &str) -> IResult<&str, Vec<Syllable>> {
 fn tokenise_pronunciation_query(query: &str) -> IResult<&str, Vec<QueryToken>> {
     separated_list_custom(
         // spaces and unknown characters
-        take_while(|c: char| !c.is_ascii_alphanumeric() && c != '?'),
+        take_while(|c: char| !c.is_ascii_alphanumeric() && c != '?' && c != '*'),
         alt((
             map(tag("?"), |_| QueryToken::WildcardSingle),
+            map(tag("*"), |_| QueryToken::WildcardMulti),
             map(
                 pair(
                     take_while1(|c: char| c.is_ascii_alphabetic()),
@@ -332,13 +1275,37 @@ fn tokenise_pronunciation_query(query: &str) -> IResult<&str, Vec<QueryToken>> {
     )(query)
 }
 fn tokenise_characters_query(query: &str) -> IResult<&str, Vec<QueryToken>> {
-    many0(
-        // spaces and unknown characters
+    many0(preceded(
+        // spaces between characters are ignored
+        multispace0,
         alt((
             map(tag("?"), |_| QueryToken::WildcardSingle),
+            map(tag("*"), |_| QueryToken::WildcardMulti),
             map(anychar, |c| QueryToken::Character(c)),
         )),
-    )(query)
+    ))(query.trim_end())
+}
+
+fn tokenise_mixed_query(query: &str) -> IResult<&str, Vec<QueryToken>> {
+    many0(preceded(
+        // spaces and apostrophes (e.g. "xi'an") between positions are ignored
+        take_while(|c: char| c.is_whitespace() || c == '\''),
+        alt((
+            map(tag("?"), |_| QueryToken::WildcardSingle),
+            map(tag("*"), |_| QueryToken::WildcardMulti),
+            map(
+                pair(
+                    take_while1(|c: char| c.is_ascii_alphabetic()),
+                    take_while_m_n(0, 1, |c: char| c.is_ascii_digit()),
+                ),
+                |(x, y): (&str, &str)| QueryToken::Pronunciation {
+                    sound: x.to_string(),
+                    tone: y.to_string(),
+                },
+            ),
+            map(anychar, QueryToken::Character),
+        )),
+    ))(query.trim_end())
 }
 
 // Modified from nom separated_list
@@ -382,13 +1349,8 @@ where
                         Err(Err::Error(_)) => return Ok((i, res)),
                         Err(e) => return Err(e),
                         Ok((i2, o)) => {
-                            if i2 == i {
-                                if empty_sep {
-                                    return Err(Err::Error(E::from_error_kind(
-                                        i2,
-                                        ErrorKind::SeparatedList,
-                                    )));
-                                }
+                            if i2 == i && empty_sep {
+                                return Err(Err::Error(E::from_error_kind(i2, ErrorKind::SeparatedList)));
                             }
 
                             res.push(o);
@@ -474,4 +1436,310 @@ mod test {
             Ok(("", vec![qc('垃'), QueryToken::WildcardSingle, qc('桶')]))
         );
     }
+
+    fn ids(page: SearchPage) -> Vec<Id> {
+        page.results.into_iter().map(|r| r.id).collect()
+    }
+
+    #[test]
+    fn definitions_whole_sense_first() {
+        let mut search = DictSearch::new();
+        search.insert_definitions(1, 0, "|rubbish bin|trash can|");
+        search.insert_definitions(2, 0, "|rubbish|");
+        search.insert_definitions(3, 0, "|to put rubbish in a bin|");
+        search.insert_definitions(4, 0, "|rubbish truck|bins|");
+        assert_eq!(ids(search.search_definitions("Rubbish bins", Page::default()).unwrap()), vec![1, 3, 4]);
+        assert_eq!(ids(search.search_definitions("rubbish", Page::default()).unwrap()), vec![2, 1, 4, 3]);
+    }
+    #[test]
+    fn definitions_stop_words_only() {
+        let mut search = DictSearch::new();
+        search.insert_definitions(1, 0, "|to be|");
+        assert!(search.search_definitions("to be", Page::default()).is_err());
+    }
+    #[test]
+    fn pronunciation_ranking() {
+        let mut search = DictSearch::new();
+        // 好人, 好, 好 (a rare reading in the same word as another pronunciation), 好好
+        search.insert_pronunciation(10, 1, "hou2 jan4", PronunciationType::Jyutping);
+        search.insert_pronunciation(11, 2, "hou2", PronunciationType::Jyutping);
+        search.insert_pronunciation(12, 2, "hou3", PronunciationType::Jyutping);
+        search.insert_pronunciation(13, 3, "hou2 hou2", PronunciationType::Jyutping);
+        search.set_frequency(3, 100);
+        let results = search.search_pronunciation("hou", PronunciationType::Jyutping, Page::default()).unwrap();
+        assert_eq!(ids(results.clone()), vec![2, 3, 1]);
+        assert!(results.results[0].score.exact);
+        assert!(!results.results[1].score.exact);
+    }
+    #[test]
+    fn characters_ranking() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "好人", "好人");
+        search.insert_characters(2, "好", "好");
+        search.insert_characters(3, "好心人", "好心人");
+        search.insert_characters(4, "好心", "好心");
+        search.insert_definitions(4, 0, "|kindness|");
+        search.insert_definitions(4, 1, "|good intentions|");
+        search.insert_definitions(1, 1, "|good person|");
+        assert_eq!(ids(search.search_characters("好", Page::default()).unwrap()), vec![2, 4, 1, 3]);
+    }
+    #[test]
+    fn pronunciation_query_multi_wildcard() {
+        assert_eq!(
+            tokenise_pronunciation_query("* tung2*"),
+            Ok((
+                "",
+                vec![QueryToken::WildcardMulti, qp("tung", "2"), QueryToken::WildcardMulti]
+            ))
+        );
+    }
+    #[test]
+    fn chars_query_multi_wildcard() {
+        assert_eq!(
+            tokenise_characters_query(" * 好 * "),
+            Ok(("", vec![QueryToken::WildcardMulti, qc('好'), QueryToken::WildcardMulti]))
+        );
+    }
+    #[test]
+    fn glob() {
+        let m = |query: &str, candidate: &str| {
+            let query_tokens = tokenise_characters_query(query).unwrap().1;
+            let candidate: Vec<char> = candidate.chars().collect();
+            glob_match(&query_tokens, &candidate, |token, c| *token == QueryToken::Character(*c))
+        };
+        assert!(m("好", "好"));
+        assert!(!m("好", "好人"));
+        assert!(m("好*", "好人"));
+        assert!(m("*人", "好人"));
+        assert!(m("*好*", "你好嗎"));
+        assert!(m("*好*好", "好好好"));
+        assert!(!m("*好?", "好"));
+        assert!(m("?*?", "好人"));
+        assert!(!m("?*?", "好"));
+        assert!(m("*", ""));
+    }
+    #[test]
+    fn pronunciation_multi_wildcard() {
+        let mut search = DictSearch::new();
+        search.insert_pronunciation(10, 1, "laap6 saap3 tung2", PronunciationType::Jyutping);
+        search.insert_pronunciation(11, 2, "tung2", PronunciationType::Jyutping);
+        search.insert_pronunciation(12, 3, "tung2 jat1", PronunciationType::Jyutping);
+        let search_ids = |query| ids(search.search_pronunciation(query, PronunciationType::Jyutping, Page::default()).unwrap());
+        assert_eq!(search_ids("* tung2"), vec![2, 1]);
+        assert_eq!(search_ids("tung2 *"), vec![2, 3]);
+        assert_eq!(search_ids("* saap *"), vec![1]);
+        assert_eq!(search_ids("? * tung"), vec![1]);
+        assert!(search.search_pronunciation("* ?", PronunciationType::Jyutping, Page::default()).is_err());
+    }
+    #[test]
+    fn characters_multi_wildcard() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "好人", "好人");
+        search.insert_characters(2, "你好", "你好");
+        search.insert_characters(3, "你好嗎", "你好吗");
+        search.insert_characters(4, "好", "好");
+        let search_ids = |query| ids(search.search_characters(query, Page::default()).unwrap());
+        assert_eq!(search_ids("* 好"), vec![4, 2]);
+        assert_eq!(search_ids("*好*"), vec![4, 1, 2, 3]);
+        assert_eq!(search_ids("你好*吗"), vec![3]);
+        assert_eq!(search_ids("*好?"), vec![1, 3]);
+    }
+    #[test]
+    fn pinyin_tone_marks() {
+        let mut search = DictSearch::new();
+        search.insert_pronunciation(10, 1, "ni3 hao3", PronunciationType::Pinyin);
+        search.insert_pronunciation(11, 2, "Xi1 an1", PronunciationType::Pinyin);
+        search.insert_pronunciation(12, 3, "lu:4 se4", PronunciationType::Pinyin);
+        let search_ids = |query| ids(search.search_pronunciation(query, PronunciationType::Pinyin, Page::default()).unwrap());
+        assert_eq!(search_ids("nǐ hǎo"), vec![1]);
+        assert_eq!(search_ids("nǐhǎo"), vec![1]);
+        assert_eq!(search_ids("xī'ān"), vec![2]);
+        assert_eq!(search_ids("xi'an"), vec![2]);
+        assert_eq!(search_ids("lǜ"), vec![3]);
+        assert_eq!(search_ids("lü se"), vec![3]);
+        assert_eq!(search_ids("lv4"), vec![3]);
+        assert_eq!(search_ids("lu:4"), vec![3]);
+    }
+    #[test]
+    fn unsegmented_pronunciation() {
+        let mut search = DictSearch::new();
+        search.insert_pronunciation(10, 1, "ni3 hao3", PronunciationType::Pinyin);
+        search.insert_pronunciation(11, 2, "Xi1 an1", PronunciationType::Pinyin);
+        search.insert_pronunciation(12, 3, "xian1", PronunciationType::Pinyin);
+        search.insert_pronunciation(13, 4, "gamjat1", PronunciationType::Jyutping);
+        let pinyin_ids = |query| ids(search.search_pronunciation(query, PronunciationType::Pinyin, Page::default()).unwrap());
+        assert_eq!(pinyin_ids("nihao"), vec![1]);
+        assert_eq!(pinyin_ids("nihao3"), vec![1]);
+        assert_eq!(pinyin_ids("xian1"), vec![3, 2]);
+        assert_eq!(
+            ids(search.search_pronunciation("gam jat1", PronunciationType::Jyutping, Page::default()).unwrap()),
+            vec![4]
+        );
+    }
+    #[test]
+    fn tone_equivalences() {
+        let mut search = DictSearch::new();
+        search.insert_pronunciation(10, 1, "sik6 faan6", PronunciationType::Jyutping);
+        search.insert_pronunciation(11, 2, "jat7", PronunciationType::Jyutping);
+        search.insert_pronunciation(20, 3, "ma1 ma5", PronunciationType::Pinyin);
+        search.insert_pronunciation(21, 4, "ba4ba", PronunciationType::Pinyin);
+        search.insert_pronunciation(22, 5, "ni hao", PronunciationType::Pinyin);
+        let jyutping_ids = |query| ids(search.search_pronunciation(query, PronunciationType::Jyutping, Page::default()).unwrap());
+        let pinyin_ids = |query| ids(search.search_pronunciation(query, PronunciationType::Pinyin, Page::default()).unwrap());
+        assert_eq!(jyutping_ids("sik9"), vec![1]);
+        assert_eq!(jyutping_ids("jat1"), vec![2]);
+        assert_eq!(pinyin_ids("ma1 ma5"), vec![3]);
+        assert_eq!(pinyin_ids("ma1 ma"), vec![3]);
+        assert_eq!(pinyin_ids("ba4 ba5"), vec![4]);
+        assert_eq!(pinyin_ids("ba4 ba0"), vec![4]);
+        assert!(pinyin_ids("ba4 ba1").is_empty());
+        // the tones are unknown
+        assert_eq!(pinyin_ids("ni3 hao3"), vec![5]);
+    }
+    #[test]
+    fn fuzzy_pronunciation() {
+        let mut search = DictSearch::new();
+        search.insert_pronunciation(10, 1, "lei5", PronunciationType::Jyutping);
+        search.insert_pronunciation(11, 2, "nei5", PronunciationType::Jyutping);
+        search.insert_pronunciation(12, 3, "nei5 dei6", PronunciationType::Jyutping);
+        search.insert_pronunciation(13, 4, "ngo5", PronunciationType::Jyutping);
+        search.set_frequency(1, 100);
+        let fuzzy_ids = |query| {
+            let mergers = Merger::defaults(PronunciationType::Jyutping);
+            ids(search.search_pronunciation_fuzzy(query, PronunciationType::Jyutping, mergers, Page::default()).unwrap())
+        };
+        assert_eq!(fuzzy_ids("nei"), vec![2, 3, 1]);
+        assert_eq!(fuzzy_ids("lei5 dei6"), vec![3]);
+        assert_eq!(fuzzy_ids("o5"), vec![4]);
+        assert!(search.search_pronunciation("o5", PronunciationType::Jyutping, Page::default()).unwrap().results.is_empty());
+    }
+    #[test]
+    fn suggestions() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "好", "好");
+        search.insert_characters(2, "號", "号");
+        search.insert_characters(3, "垃圾", "垃圾");
+        search.insert_characters(4, "垃圾桶", "垃圾桶");
+        search.insert_pronunciation(10, 1, "hou2", PronunciationType::Jyutping);
+        search.insert_pronunciation(11, 2, "hou6", PronunciationType::Jyutping);
+        search.insert_pronunciation(12, 3, "laap6 saap3", PronunciationType::Jyutping);
+        search.insert_pronunciation(13, 4, "la1 ji1 tong3", PronunciationType::Pinyin);
+        search.set_frequency(1, 100);
+        search.set_frequency(2, 50);
+        search.set_frequency(4, 10);
+        search.rank_suggestions();
+        assert_eq!(search.suggest("hou", 10), vec![1, 2]);
+        assert_eq!(search.suggest("hou6", 10), vec![2]);
+        assert_eq!(search.suggest("垃", 10), vec![4, 3]);
+        assert_eq!(search.suggest("laap6 s", 10), vec![3]);
+        assert_eq!(search.suggest("lā", 10), vec![4]);
+        assert_eq!(search.suggest("laap saap3", 10), vec![3]);
+        assert!(search.suggest("laap saap1", 10).is_empty());
+        assert_eq!(search.suggest("lāji t", 10), vec![4]);
+        assert_eq!(search.suggest("l", 1), vec![4]);
+        assert!(search.suggest(" ", 10).is_empty());
+    }
+    #[test]
+    fn remove_and_update() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "好", "好");
+        search.insert_characters(2, "號", "号");
+        search.insert_pronunciation(10, 1, "hou2", PronunciationType::Jyutping);
+        search.insert_pronunciation(11, 1, "hou3", PronunciationType::Jyutping);
+        search.insert_pronunciation(12, 2, "hou6", PronunciationType::Jyutping);
+        search.insert_definitions(1, 1, "good");
+        search.insert_definitions(2, 1, "number");
+        search.rank_suggestions();
+
+        search.remove_pronunciation(10);
+        let page = search.search_pronunciation("hou2", PronunciationType::Jyutping, Page::default()).unwrap();
+        assert!(page.results.is_empty());
+        // the word still has another pronunciation starting with "hou"
+        assert_eq!(search.suggest("hou", 10), vec![1, 2]);
+
+        search.update_characters(2, "号", "号");
+        assert!(search.search_characters("號", Page::default()).unwrap().results.is_empty());
+        assert_eq!(ids(search.search_characters("号", Page::default()).unwrap()), vec![2]);
+
+        search.remove_word(1);
+        assert!(search.search_characters("好", Page::default()).unwrap().results.is_empty());
+        assert!(search.search_definitions("good", Page::default()).unwrap().results.is_empty());
+        let page = search.search_pronunciation("hou", PronunciationType::Jyutping, Page::default()).unwrap();
+        assert_eq!(ids(page), vec![2]);
+        assert_eq!(search.suggest("hou", 10), vec![2]);
+    }
+    #[test]
+    fn starting_with() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "好人", "好人");
+        search.insert_characters(2, "人好", "人好");
+        assert_eq!(ids(search.search_starting_with('好', Page::default()).unwrap()), vec![1]);
+        assert!(search.search_starting_with('?', Page::default()).is_err());
+        assert!(search.search_starting_with('*', Page::default()).is_err());
+    }
+    #[test]
+    fn mixed_query_tokens() {
+        assert_eq!(
+            tokenise_mixed_query("垃 saap3 ?"),
+            Ok(("", vec![qc('垃'), qp("saap", "3"), QueryToken::WildcardSingle]))
+        );
+    }
+    #[test]
+    fn mixed_search() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "垃圾桶", "垃圾桶");
+        search.insert_characters(2, "垃圾", "垃圾");
+        search.insert_characters(3, "好", "好");
+        search.insert_pronunciation(10, 1, "laap6 saap3 tung2", PronunciationType::Jyutping);
+        search.insert_pronunciation(11, 2, "laap6 saap3", PronunciationType::Jyutping);
+        search.insert_pronunciation(12, 3, "hou2", PronunciationType::Jyutping);
+        search.insert_pronunciation(13, 3, "hou3", PronunciationType::Jyutping);
+        let mixed_ids = |query| ids(search.search_mixed(query, PronunciationType::Jyutping, Page::default()).unwrap());
+        assert_eq!(mixed_ids("垃 saap3 ?"), vec![1]);
+        assert_eq!(mixed_ids("垃 saap3"), vec![2, 1]);
+        assert_eq!(mixed_ids("laap 圾 tung"), vec![1]);
+        assert!(mixed_ids("垃 saap1").is_empty());
+        assert_eq!(mixed_ids("hou3"), vec![3]);
+        assert_eq!(mixed_ids("好"), vec![3]);
+    }
+    #[test]
+    fn auto_search() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "是", "是");
+        search.insert_characters(2, "事", "事");
+        search.insert_pronunciation(10, 1, "shi4", PronunciationType::Pinyin);
+        search.insert_pronunciation(11, 1, "si6", PronunciationType::Jyutping);
+        search.insert_pronunciation(12, 2, "si6", PronunciationType::Jyutping);
+        search.insert_definitions(2, 0, "|matter|thing|");
+        let results = search.search_auto("si", Page::default()).unwrap();
+        // 事 is in a dictionary, so it comes first
+        assert_eq!(results.results.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(results.results[0].kinds, vec![QueryKind::Jyutping]);
+        assert_eq!(search.search_auto("matter", Page::default()).unwrap().results[0].kinds, vec![QueryKind::English]);
+        assert_eq!(search.search_auto("是", Page::default()).unwrap().results[0].kinds, vec![QueryKind::Characters]);
+        assert!(search.search_auto("?", Page::default()).is_err());
+
+        search.insert_characters(3, "垃圾", "垃圾");
+        search.insert_pronunciation(13, 3, "laap6 saap3", PronunciationType::Jyutping);
+        search.insert_pronunciation(14, 3, "la1 ji1", PronunciationType::Pinyin);
+        for query in &["垃 saap3", "垃 ji"] {
+            let results = search.search_auto(query, Page::default()).unwrap();
+            assert_eq!(results.results.iter().map(|r| r.id).collect::<Vec<_>>(), vec![3]);
+            assert_eq!(results.results[0].kinds, vec![QueryKind::Mixed]);
+        }
+    }
+    #[test]
+    fn pages() {
+        let mut search = DictSearch::new();
+        for id in 0..300 {
+            search.insert_characters(id, "好", "好");
+            search.set_frequency(id, id);
+        }
+        let page = |offset, limit| search.search_characters("好", Page { offset, limit }).unwrap();
+        assert_eq!(page(0, 3).total, 300);
+        assert_eq!(ids(page(0, 3)), vec![299, 298, 297]);
+        assert_eq!(ids(page(3, 2)), vec![296, 295]);
+        assert_eq!(page(0, 1000).results.len(), MAX_PAGE_SIZE);
+        assert!(page(300, 10).results.is_empty());
+    }
 }
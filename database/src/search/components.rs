@@ -0,0 +1,174 @@
+//! Finding characters by their components (e.g. 木 and 口 for 杏 and 呆), using
+//! Ideographic Description Sequences which describe how each character is built.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use super::{rank, DictSearch, Id, MatchOptions, Page, SearchError, SearchPage, MAX_RESULTS};
+
+/// The most components which may be searched for at once.
+pub const MAX_COMPONENTS: usize = 16;
+
+/// Characters and words containing all the components of a query.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct ComponentSearch {
+    /// The characters with every component, by stroke count.
+    pub characters: Vec<char>,
+    /// The words containing any of `characters`, best first.
+    pub words: SearchPage,
+}
+
+/// Check whether a character is an ideographic description character, such as ⿰,
+/// which describes the layout of components rather than being a component.
+fn is_description_character(c: char) -> bool {
+    ('\u{2FF0}'..='\u{2FFF}').contains(&c) || c == '\u{31EF}'
+}
+
+impl DictSearch {
+    /// Set the direct components of a character from its Ideographic Description
+    /// Sequence, e.g. "⿰木木". `index_components` must be called afterwards.
+    pub fn insert_decomposition(&mut self, character: char, decomposition: &str) {
+        let mut components: Vec<char> = decomposition
+            .chars()
+            .filter(|c| !is_description_character(*c) && *c != character && !c.is_whitespace())
+            .collect();
+        components.sort();
+        components.dedup();
+        self.decompositions.insert(character, components.into_boxed_slice());
+    }
+    /// Rebuild the index of the characters containing each component, including
+    /// components of components (so 木 is found in 森 through 林 as well as directly).
+    pub fn index_components(&mut self) {
+        let mut all_components = HashMap::new();
+        let mut component_map: HashMap<char, Vec<char>> = HashMap::new();
+        for character in self.decompositions.keys() {
+            let components = self.collect_components(*character, &mut all_components, &mut HashSet::new());
+            for component in components {
+                let characters = component_map.entry(component).or_default();
+                if let Err(i) = characters.binary_search(character) {
+                    characters.insert(i, *character);
+                }
+            }
+        }
+        self.component_map = component_map;
+    }
+    /// All the components of a character, memoised in `done`. `visiting` guards
+    /// against cycles in faulty data.
+    fn collect_components(
+        &self,
+        character: char,
+        done: &mut HashMap<char, Vec<char>>,
+        visiting: &mut HashSet<char>,
+    ) -> Vec<char> {
+        if let Some(components) = done.get(&character) {
+            return components.clone();
+        }
+        let direct = match self.decompositions.get(&character) {
+            Some(direct) => direct,
+            None => return Vec::new(),
+        };
+        if !visiting.insert(character) {
+            return direct.to_vec();
+        }
+        let mut components = direct.to_vec();
+        for component in direct.iter() {
+            components.extend(self.collect_components(*component, done, visiting));
+        }
+        components.sort();
+        components.dedup();
+        components.retain(|c| *c != character);
+        visiting.remove(&character);
+        done.insert(character, components.clone());
+        components
+    }
+    /// Search for characters containing every component in the query (e.g. "木口"),
+    /// and the words containing those characters. At most `MAX_RESULTS` characters
+    /// are found, those with the fewest strokes.
+    pub fn search_components(&self, query: &str, page: Page) -> Result<ComponentSearch, SearchError> {
+        let mut components: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+        components.sort();
+        components.dedup();
+        if components.is_empty() || components.len() > MAX_COMPONENTS {
+            return Err(SearchError::InvalidInput);
+        }
+
+        let mut lists = Vec::with_capacity(components.len());
+        for component in &components {
+            match self.component_map.get(component) {
+                Some(characters) => lists.push(characters),
+                None => {
+                    return Ok(ComponentSearch {
+                        characters: Vec::new(),
+                        words: SearchPage {
+                            total: 0,
+                            results: Vec::new(),
+                        },
+                    })
+                }
+            }
+        }
+        // intersect the lists, starting from the shortest
+        lists.sort_by_key(|characters| characters.len());
+        let mut characters: Vec<char> = lists[0]
+            .iter()
+            .filter(|c| lists[1..].iter().all(|characters| characters.binary_search(*c).is_ok()))
+            .cloned()
+            .collect();
+        characters.sort_by_key(|c| (self.stroke_info.get(c).map_or(u8::MAX, |info| info.total_strokes), *c));
+        characters.truncate(MAX_RESULTS);
+
+        let mut word_ids: Vec<Id> = characters
+            .iter()
+            .flat_map(|character| self.words_with_character(*character, MatchOptions::STRICT))
+            .collect();
+        word_ids.sort_unstable();
+        word_ids.dedup();
+        let results = word_ids
+            .into_iter()
+            .filter_map(|id| {
+                let length = self.characters_map.get(&id)?.traditional.chars().count();
+                Some((id, self.score(id, length == 1, length)))
+            })
+            .collect();
+        Ok(ComponentSearch {
+            characters,
+            words: rank(results, page),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn components() {
+        let mut search = DictSearch::new();
+        search.insert_decomposition('杏', "⿱木口");
+        search.insert_decomposition('呆', "⿱口木");
+        search.insert_decomposition('林', "⿰木木");
+        search.insert_decomposition('森', "⿱木林");
+        search.insert_decomposition('困', "⿴囗木");
+        search.insert_decomposition('木', "木");
+        search.index_components();
+        search.insert_characters(1, "杏", "杏");
+        search.insert_characters(2, "杏仁", "杏仁");
+        search.insert_characters(3, "森林", "森林");
+
+        let result = search.search_components("木 口", Page::default()).unwrap();
+        assert_eq!(result.characters, vec!['呆', '杏']);
+        let ids: Vec<Id> = result.words.results.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        // components of components
+        let result = search.search_components("木", Page::default()).unwrap();
+        assert_eq!(result.characters, vec!['呆', '困', '杏', '林', '森']);
+        assert_eq!(result.words.total, 3);
+
+        assert!(search.search_components("木水", Page::default()).unwrap().characters.is_empty());
+        assert!(search.search_components(" ", Page::default()).is_err());
+        let too_many: String = (0..=MAX_COMPONENTS as u32).map(|i| std::char::from_u32(0x4E00 + i).unwrap()).collect();
+        assert!(search.search_components(&too_many, Page::default()).is_err());
+    }
+}
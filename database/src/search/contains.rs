@@ -0,0 +1,150 @@
+//! Finding words which contain a run of characters anywhere, e.g. 好人 in 老好人,
+//! using the positions of each character in words.
+
+use std::collections::HashMap;
+
+use super::{
+    insert_best, rank, tokenise_characters_query, Characters, DictSearch, Id, MatchOptions, Page, QueryToken, Script,
+    SearchError, SearchPage,
+};
+
+impl DictSearch {
+    /// Add the positions of a word's characters (in either script) to the index.
+    pub(super) fn insert_positions(&mut self, id: Id, characters: &Characters) {
+        for (position, (trad, simp)) in characters.pairs().into_iter().enumerate() {
+            let key = (id, position as u16);
+            for character in if trad == simp { vec![trad] } else { vec![trad, simp] } {
+                let positions = self.character_positions.entry(character).or_default();
+                if let Err(i) = positions.binary_search(&key) {
+                    positions.insert(i, key);
+                }
+            }
+        }
+    }
+    pub(super) fn remove_positions(&mut self, id: Id, characters: &Characters) {
+        for (position, (trad, simp)) in characters.pairs().into_iter().enumerate() {
+            let key = (id, position as u16);
+            for character in if trad == simp { vec![trad] } else { vec![trad, simp] } {
+                if let Some(positions) = self.character_positions.get_mut(&character) {
+                    if let Ok(i) = positions.binary_search(&key) {
+                        positions.remove(i);
+                    }
+                    if positions.is_empty() {
+                        self.character_positions.remove(&character);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Search for words containing the characters of the query together, anywhere in
+    /// the word. Each character may be in traditional or simplified form (unless a
+    /// script is chosen), or (unless matching strictly) a variant. `?` matches any one
+    /// character, and `*` is not allowed. Words which are exactly the query come first,
+    /// then shorter words.
+    pub fn search_characters_containing(
+        &self,
+        query: &str,
+        options: MatchOptions,
+        page: Page,
+    ) -> Result<SearchPage, SearchError> {
+        let query_tokens = tokenise_characters_query(query).map_err(|_| SearchError::InvalidInput)?.1;
+        if query_tokens.contains(&QueryToken::WildcardMulti) {
+            return Err(SearchError::InvalidInput);
+        }
+        // the characters in the query, with their offsets from its start
+        let mut characters = Vec::new();
+        for (offset, token) in query_tokens.iter().enumerate() {
+            if let QueryToken::Character(c) = token {
+                let positions = self.positions_of(*c, options);
+                if positions.is_empty() {
+                    // no word has this character
+                    return Ok(rank(HashMap::new(), page));
+                }
+                characters.push((offset as u16, positions));
+            }
+        }
+        // start from the least common character, and look up the others at their
+        // positions relative to it
+        characters.sort_by_key(|(_, positions)| positions.len());
+        let (anchor_offset, anchor_positions) = match characters.first() {
+            Some((offset, positions)) => (*offset, positions),
+            None => return Err(SearchError::InvalidInput),
+        };
+        let query_length = query_tokens.len();
+
+        let mut results = HashMap::new();
+        for (id, position) in anchor_positions.iter() {
+            let start = match position.checked_sub(anchor_offset) {
+                Some(start) => start,
+                None => continue,
+            };
+            let found = characters[1..]
+                .iter()
+                .all(|(offset, positions)| positions.binary_search(&(*id, start + offset)).is_ok());
+            if !found {
+                continue;
+            }
+            let pairs = match self.characters_map.get(id) {
+                Some(word) => word.pairs(),
+                None => continue,
+            };
+            let length = pairs.len();
+            // trailing `?` wildcards must be within the word
+            if start as usize + query_length > length {
+                continue;
+            }
+            // the index has both forms of each character, so check the chosen form
+            if options.script != Script::Any {
+                let in_script = query_tokens.iter().enumerate().all(|(offset, token)| match token {
+                    QueryToken::Character(c) => self.characters_match(*c, pairs[start as usize + offset], options),
+                    _ => true,
+                });
+                if !in_script {
+                    continue;
+                }
+            }
+            insert_best(&mut results, *id, self.score(*id, length == query_length, length));
+        }
+        Ok(rank(results, page))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ids(page: SearchPage) -> Vec<Id> {
+        page.results.into_iter().map(|r| r.id).collect()
+    }
+
+    #[test]
+    fn contains() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "好人", "好人");
+        search.insert_characters(2, "老好人", "老好人");
+        search.insert_characters(3, "好好人人", "好好人人");
+        search.insert_characters(4, "人好", "人好");
+        search.insert_characters(5, "好", "好");
+        search.insert_characters(6, "壞人", "坏人");
+        let search_ids = |query| {
+            ids(search.search_characters_containing(query, MatchOptions::default(), Page::default()).unwrap())
+        };
+
+        assert_eq!(search_ids("好人"), vec![1, 2, 3]);
+        assert_eq!(search_ids("好"), vec![5, 1, 4, 2, 3]);
+        assert_eq!(search_ids("坏人"), vec![6]);
+        assert_eq!(search_ids("壞"), vec![6]);
+        assert_eq!(search_ids("?人"), vec![1, 6, 2, 3]);
+        assert_eq!(search_ids("好?"), vec![1, 2, 3]);
+        assert!(search_ids("人好人").is_empty());
+        assert!(search_ids("水").is_empty());
+        let options = MatchOptions::default();
+        assert!(search.search_characters_containing("好*", options, Page::default()).is_err());
+        assert!(search.search_characters_containing("??", options, Page::default()).is_err());
+
+        search.remove_characters(2);
+        let page = search.search_characters_containing("好人", MatchOptions::default(), Page::default());
+        assert_eq!(ids(page.unwrap()), vec![1, 3]);
+    }
+}
@@ -0,0 +1,649 @@
+//! Converting pronunciations between romanizations: jyutping, Yale and Cantonese
+//! Pinyin for Cantonese, and numbered pinyin, pinyin with tone marks and Zhuyin
+//! (Bopomofo) for Mandarin. Both can also be written in IPA.
+//!
+//! Text is converted through the system stored in the index (jyutping or numbered
+//! pinyin), so any two systems for the same language can be converted between.
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use super::pinyin::{split_tone_mark, TONE_MARKS};
+use super::syllables::{is_syllable, JYUTPING_INITIALS, PINYIN_SYLLABLES};
+use super::{parse_pronunciation, tokenise_pronunciation, PronunciationType, SearchError, Syllable};
+
+/// A system for writing pronunciations.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Romanization {
+    Jyutping,
+    /// Yale with tone marks, e.g. "néih hóu".
+    Yale,
+    /// The Cantonese Pinyin of the Education Bureau, e.g. "dzoek8".
+    CantonesePinyin,
+    CantoneseIpa,
+    /// Pinyin with tone numbers, e.g. "ni3 hao3".
+    Pinyin,
+    /// Pinyin with tone marks, e.g. "nǐ hǎo".
+    PinyinMarks,
+    Zhuyin,
+    MandarinIpa,
+}
+
+impl From<PronunciationType> for Romanization {
+    /// The system a pronunciation type is stored in.
+    fn from(pronunciation_type: PronunciationType) -> Self {
+        match pronunciation_type {
+            PronunciationType::Jyutping => Self::Jyutping,
+            PronunciationType::Pinyin => Self::Pinyin,
+        }
+    }
+}
+
+impl Romanization {
+    /// The language of the system, as the pronunciation type it is converted through.
+    pub fn pronunciation_type(self) -> PronunciationType {
+        match self {
+            Self::Jyutping | Self::Yale | Self::CantonesePinyin | Self::CantoneseIpa => PronunciationType::Jyutping,
+            Self::Pinyin | Self::PinyinMarks | Self::Zhuyin | Self::MandarinIpa => PronunciationType::Pinyin,
+        }
+    }
+    /// Split text written in this system into syllables in the stored system.
+    /// IPA can only be written, not read.
+    fn parse(self, text: &str) -> Result<Vec<Syllable>, SearchError> {
+        let pronunciation_type = self.pronunciation_type();
+        let syllables = match self {
+            Self::Jyutping | Self::Pinyin | Self::PinyinMarks => parse_pronunciation(text, pronunciation_type),
+            Self::Yale => parse_yale(text, "3")?,
+            Self::CantonesePinyin => {
+                let (_, tokens) = tokenise_pronunciation(&text.to_lowercase()).map_err(|_| SearchError::InvalidInput)?;
+                tokens
+                    .into_iter()
+                    .map(|token| Syllable {
+                        sound: cantonese_pinyin_to_jyutping(&token.sound),
+                        tone: pronunciation_type.normalise_tone(&token.tone),
+                    })
+                    .collect()
+            }
+            Self::Zhuyin => parse_zhuyin(text, "1")?,
+            Self::CantoneseIpa | Self::MandarinIpa => return Err(SearchError::InvalidInput),
+        };
+
+        check_syllables(&syllables, pronunciation_type)?;
+        Ok(syllables)
+    }
+    /// Rewrite a pronunciation query written in this system in the stored system,
+    /// keeping wildcards, e.g. "néih ?" in Yale as "nei5 ?". As tone marks are awkward
+    /// to type, Yale and Zhuyin syllables with no tone marked match any tone.
+    /// Syllables must be separated by spaces. Jyutping and pinyin are left as they are.
+    pub fn convert_query(self, query: &str) -> Result<String, SearchError> {
+        let parse_word: fn(&str) -> Result<Vec<Syllable>, SearchError> = match self {
+            Self::Yale => |word: &str| parse_yale(word, ""),
+            Self::Zhuyin => |word: &str| parse_zhuyin(word, ""),
+            _ => return Ok(query.to_string()),
+        };
+        let mut tokens = Vec::new();
+        for word in query.split_whitespace() {
+            if word == "?" || word == "*" {
+                tokens.push(word.to_string());
+                continue;
+            }
+            let syllables = parse_word(word)?;
+            check_syllables(&syllables, self.pronunciation_type())?;
+            tokens.extend(syllables.iter().map(|syllable| format!("{}{}", syllable.sound, syllable.tone)));
+        }
+        Ok(tokens.join(" "))
+    }
+    /// Write a syllable of the stored system in this system.
+    fn render(self, syllable: &Syllable) -> String {
+        let (sound, tone) = (&*syllable.sound, &*syllable.tone);
+        match self {
+            Self::Jyutping => format!("{}{}", sound, tone),
+            Self::Yale => jyutping_to_yale(sound, tone),
+            Self::CantonesePinyin => jyutping_to_cantonese_pinyin(sound, tone),
+            Self::CantoneseIpa => jyutping_to_ipa(sound, tone),
+            Self::Pinyin => format!("{}{}", sound.replace('v', "ü"), tone),
+            Self::PinyinMarks => pinyin_with_mark(sound, tone),
+            Self::Zhuyin => pinyin_to_zhuyin(sound, tone),
+            Self::MandarinIpa => pinyin_to_ipa(sound, tone),
+        }
+    }
+}
+
+/// Convert a pronunciation from one system to another for the same language,
+/// e.g. "nei5 hou2" in jyutping to "néih hóu" in Yale. Syllables are separated by spaces.
+pub fn convert(text: &str, from: Romanization, to: Romanization) -> Result<String, SearchError> {
+    if from.pronunciation_type() != to.pronunciation_type() {
+        return Err(SearchError::InvalidInput);
+    }
+    let syllables: Vec<String> = from.parse(text)?.iter().map(|syllable| to.render(syllable)).collect();
+    Ok(syllables.join(" "))
+}
+
+/// Check that there are syllables, and that they are valid in the stored system.
+fn check_syllables(syllables: &[Syllable], pronunciation_type: PronunciationType) -> Result<(), SearchError> {
+    let valid = |syllable: &Syllable| {
+        is_syllable(pronunciation_type, &syllable.sound)
+            && (syllable.tone.is_empty() || pronunciation_type.tones().contains(&&*syllable.tone))
+    };
+    if syllables.is_empty() || !syllables.iter().all(valid) {
+        return Err(SearchError::InvalidInput);
+    }
+    Ok(())
+}
+
+const SYLLABIC_NASALS: &[&str] = &["m", "ng", "hm", "hng"];
+
+const YALE_INITIALS: &[&str] = &[
+    "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "ng", "h", "gw", "kw", "w", "j", "ch", "s", "y",
+];
+
+const CANTONESE_PINYIN_INITIALS: &[&str] = &[
+    "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "ng", "h", "gw", "kw", "w", "dz", "ts", "s", "j",
+];
+
+const PINYIN_INITIALS: &[&str] = &[
+    "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "h", "j", "q", "x", "zh", "ch", "sh", "r", "z", "c", "s",
+];
+
+/// Combining diacritics in the same order as `TONE_MARKS`, for letters with no precomposed form.
+const COMBINING_MARKS: [char; 4] = ['\u{304}', '\u{301}', '\u{30C}', '\u{300}'];
+
+/// Nasals with a precomposed tone mark, which Yale uses for syllabic nasals (e.g. "ǹgh"),
+/// with the index of the mark in `COMBINING_MARKS`.
+const MARKED_NASALS: [(char, char, usize); 3] = [('ḿ', 'm', 1), ('ń', 'n', 1), ('ǹ', 'n', 3)];
+
+/// Split a syllable into its initial (the longest one it starts with) and its final.
+fn split_initial<'a>(sound: &'a str, initials: &[&str]) -> (&'a str, &'a str) {
+    let length = initials
+        .iter()
+        .filter(|initial| sound.starts_with(**initial) && sound.len() > initial.len())
+        .map(|initial| initial.len())
+        .max()
+        .unwrap_or(0);
+    sound.split_at(length)
+}
+
+/// Find a value in a table of pairs, keeping the key if it is not there.
+fn lookup<'a>(table: &[(&str, &'a str)], key: &'a str) -> &'a str {
+    table.iter().find(|(k, _)| *k == key).map_or(key, |(_, value)| *value)
+}
+
+/// Add a tone mark (an index into `TONE_MARKS`) to a letter.
+fn with_mark(letter: char, mark: usize) -> String {
+    match TONE_MARKS.iter().find(|(base, _)| *base == letter) {
+        Some((_, marked)) => marked[mark].to_string(),
+        None => format!("{}{}", letter, COMBINING_MARKS[mark]),
+    }
+}
+
+/// Split a jyutping syllable into its initial and final. Syllabic nasals have no final.
+fn jyutping_parts(sound: &str) -> (&str, &str) {
+    if SYLLABIC_NASALS.contains(&sound) {
+        return (sound, "");
+    }
+    split_initial(sound, JYUTPING_INITIALS)
+}
+
+fn jyutping_to_yale(sound: &str, tone: &str) -> String {
+    let (initial, final_) = jyutping_parts(sound);
+    let initial = match initial {
+        "z" => "j",
+        "c" => "ch",
+        // "jyu" is written "yu"
+        "j" if final_.starts_with("yu") => "",
+        "j" => "y",
+        initial => initial,
+    };
+    let final_ = match final_ {
+        "aa" => "a",
+        "oe" => "eu",
+        "oeng" => "eung",
+        "oek" => "euk",
+        "eoi" => "eui",
+        "eon" => "eun",
+        "eot" => "eut",
+        final_ => final_,
+    };
+    // High tones are marked with a macron or acute accent, and low tones with an
+    // h after the vowels (and a grave or acute accent).
+    let (mark, low) = match tone {
+        "1" => (Some(0), false),
+        "2" => (Some(1), false),
+        "4" => (Some(3), true),
+        "5" => (Some(1), true),
+        "6" => (None, true),
+        _ => (None, false),
+    };
+
+    let letters: Vec<char> = format!("{}{}", initial, final_).chars().collect();
+    let is_vowel = |c: &char| "aeiou".contains(*c);
+    // the mark goes on the first vowel, or the nasal of a syllabic nasal
+    let (marked, end) = match letters.iter().position(is_vowel) {
+        Some(first) => (first, first + letters[first..].iter().take_while(|c| is_vowel(c)).count()),
+        None => (letters.iter().position(|c| *c == 'm' || *c == 'n').unwrap_or(0), letters.len()),
+    };
+    let mut yale = String::new();
+    for (i, c) in letters.iter().enumerate() {
+        if i == end && low {
+            yale.push('h');
+        }
+        match mark {
+            Some(mark) if i == marked => yale.push_str(&with_mark(*c, mark)),
+            _ => yale.push(*c),
+        }
+    }
+    if end == letters.len() && low {
+        yale.push('h');
+    }
+    yale
+}
+
+/// Read Yale syllables separated by spaces, hyphens or apostrophes. Syllables
+/// with no tone marked are given `unmarked_tone`.
+fn parse_yale(text: &str, unmarked_tone: &str) -> Result<Vec<Syllable>, SearchError> {
+    text.split(|c: char| c.is_whitespace() || c == '-' || c == '\'')
+        .filter(|word| !word.is_empty())
+        .map(|word| yale_to_jyutping(word, unmarked_tone))
+        .collect()
+}
+
+fn yale_to_jyutping(word: &str, unmarked_tone: &str) -> Result<Syllable, SearchError> {
+    let mut letters = String::new();
+    let mut mark = None;
+    let mut low = false;
+    for c in word.chars().flat_map(char::to_lowercase) {
+        let (mut base, tone) = split_tone_mark(c);
+        if let Some(tone) = tone {
+            mark = Some(tone as usize - 1);
+        } else if let Some((_, nasal, i)) = MARKED_NASALS.iter().find(|(marked, _, _)| *marked == c) {
+            base = *nasal;
+            mark = Some(*i);
+        } else if let Some(i) = COMBINING_MARKS.iter().position(|m| *m == c) {
+            mark = Some(i);
+            continue;
+        }
+        // an h after the vowels (or a syllabic nasal) marks a low tone
+        let has_vowel = letters.contains(|c: char| "aeiou".contains(c));
+        let after_nucleus = letters.ends_with(|c: char| "aeiou".contains(c))
+            || (!has_vowel && letters.ends_with(['m', 'g']));
+        if base == 'h' && after_nucleus {
+            low = true;
+        } else {
+            letters.push(base);
+        }
+    }
+    let tone = match (mark, low) {
+        (Some(0), false) | (Some(3), false) => "1",
+        (Some(1), false) => "2",
+        (None, false) => unmarked_tone,
+        (Some(3), true) => "4",
+        (Some(1), true) => "5",
+        (None, true) => "6",
+        _ => return Err(SearchError::InvalidInput),
+    };
+
+    let sound = if SYLLABIC_NASALS.contains(&&*letters) {
+        letters
+    } else if letters == "yu" || letters == "yun" || letters == "yut" {
+        // "jyu" is written "yu", but "yung" is "jung"
+        format!("j{}", letters)
+    } else {
+        let (initial, final_) = split_initial(&letters, YALE_INITIALS);
+        let initial = match initial {
+            "j" => "z",
+            "ch" => "c",
+            "y" => "j",
+            initial => initial,
+        };
+        let final_ = match final_ {
+            "a" => "aa",
+            "eu" => "oe",
+            "eung" => "oeng",
+            "euk" => "oek",
+            "eui" => "eoi",
+            "eun" => "eon",
+            "eut" => "eot",
+            final_ => final_,
+        };
+        format!("{}{}", initial, final_)
+    };
+    Ok(Syllable {
+        sound,
+        tone: tone.to_string(),
+    })
+}
+
+fn jyutping_to_cantonese_pinyin(sound: &str, tone: &str) -> String {
+    let (initial, final_) = jyutping_parts(sound);
+    let initial = match initial {
+        "z" => "dz",
+        "c" => "ts",
+        initial => initial,
+    };
+    let final_ = match final_ {
+        "aa" => "a",
+        "eoi" => "oey",
+        "eon" => "oen",
+        "eot" => "oet",
+        "yu" => "y",
+        "yun" => "yn",
+        "yut" => "yt",
+        final_ => final_,
+    };
+    // syllables ending in a stop have their own tone numbers
+    let stop = final_.ends_with(|c: char| "ptk".contains(c));
+    let tone = match tone {
+        "1" if stop => "7",
+        "3" if stop => "8",
+        "6" if stop => "9",
+        tone => tone,
+    };
+    format!("{}{}{}", initial, final_, tone)
+}
+
+fn cantonese_pinyin_to_jyutping(sound: &str) -> String {
+    if SYLLABIC_NASALS.contains(&sound) {
+        return sound.to_string();
+    }
+    let (initial, final_) = split_initial(sound, CANTONESE_PINYIN_INITIALS);
+    let initial = match initial {
+        "dz" => "z",
+        "ts" => "c",
+        initial => initial,
+    };
+    let final_ = match final_ {
+        "a" => "aa",
+        "oey" => "eoi",
+        "oen" => "eon",
+        "oet" => "eot",
+        "y" => "yu",
+        "yn" => "yun",
+        "yt" => "yut",
+        final_ => final_,
+    };
+    format!("{}{}", initial, final_)
+}
+
+const CANTONESE_IPA_INITIALS: &[(&str, &str)] = &[
+    ("b", "p"), ("p", "pʰ"), ("d", "t"), ("t", "tʰ"), ("g", "k"), ("k", "kʰ"), ("ng", "ŋ"),
+    ("gw", "kʷ"), ("kw", "kʷʰ"), ("z", "ts"), ("c", "tsʰ"),
+];
+
+const CANTONESE_IPA_FINALS: &[(&str, &str)] = &[
+    ("aa", "aː"), ("aai", "aːi"), ("aau", "aːu"), ("aam", "aːm"), ("aan", "aːn"), ("aang", "aːŋ"),
+    ("aap", "aːp̚"), ("aat", "aːt̚"), ("aak", "aːk̚"), ("a", "ɐ"), ("ai", "ɐi"), ("au", "ɐu"),
+    ("am", "ɐm"), ("an", "ɐn"), ("ang", "ɐŋ"), ("ap", "ɐp̚"), ("at", "ɐt̚"), ("ak", "ɐk̚"),
+    ("e", "ɛː"), ("ei", "ei"), ("eu", "ɛːu"), ("em", "ɛːm"), ("en", "ɛːn"), ("eng", "ɛːŋ"),
+    ("ep", "ɛːp̚"), ("et", "ɛːt̚"), ("ek", "ɛːk̚"), ("i", "iː"), ("iu", "iːu"), ("im", "iːm"),
+    ("in", "iːn"), ("ing", "ɪŋ"), ("ip", "iːp̚"), ("it", "iːt̚"), ("ik", "ɪk̚"), ("o", "ɔː"),
+    ("oi", "ɔːy"), ("ou", "ou"), ("on", "ɔːn"), ("ong", "ɔːŋ"), ("ot", "ɔːt̚"), ("ok", "ɔːk̚"),
+    ("oe", "œː"), ("oeng", "œːŋ"), ("oek", "œːk̚"), ("eoi", "ɵy"), ("eon", "ɵn"), ("eot", "ɵt̚"),
+    ("u", "uː"), ("ui", "uːy"), ("un", "uːn"), ("ung", "ʊŋ"), ("ut", "uːt̚"), ("uk", "ʊk̚"),
+    ("yu", "yː"), ("yun", "yːn"), ("yut", "yːt̚"),
+];
+
+fn jyutping_to_ipa(sound: &str, tone: &str) -> String {
+    let syllable = match sound {
+        "m" => "m̩".to_string(),
+        "ng" => "ŋ̍".to_string(),
+        "hm" => "hm̩".to_string(),
+        "hng" => "hŋ̍".to_string(),
+        _ => {
+            let (initial, final_) = jyutping_parts(sound);
+            format!("{}{}", lookup(CANTONESE_IPA_INITIALS, initial), lookup(CANTONESE_IPA_FINALS, final_))
+        }
+    };
+    let contour = match tone {
+        "1" => "˥",
+        "2" => "˧˥",
+        "3" => "˧",
+        "4" => "˨˩",
+        "5" => "˩˧",
+        "6" => "˨",
+        _ => "",
+    };
+    format!("{}{}", syllable, contour)
+}
+
+fn pinyin_with_mark(sound: &str, tone: &str) -> String {
+    let mut letters: Vec<String> = sound
+        .chars()
+        .map(|c| if c == 'v' { 'ü' } else { c })
+        .map(String::from)
+        .collect();
+    let mark = match tone {
+        "1" => 0,
+        "2" => 1,
+        "3" => 2,
+        "4" => 3,
+        _ => return letters.concat(),
+    };
+    // the mark goes on a or e, the o of ou, or otherwise the last vowel
+    let position = letters
+        .iter()
+        .position(|c| c == "a" || c == "e")
+        .or_else(|| if sound.contains("ou") { letters.iter().position(|c| c == "o") } else { None })
+        .or_else(|| letters.iter().rposition(|c| ["i", "o", "u", "ü"].contains(&&**c)));
+    if let Some(i) = position {
+        letters[i] = with_mark(letters[i].chars().next().unwrap(), mark);
+    }
+    letters.concat()
+}
+
+/// Split a pinyin syllable into its initial and its final, written in full without
+/// the abbreviations of the spelling, e.g. "you" -> ("", "iou"), "jun" -> ("j", "vn").
+/// The final of "zhi", "ci" etc. is empty.
+fn pinyin_parts(sound: &str) -> (&str, String) {
+    if let Some(rest) = sound.strip_prefix('y') {
+        let final_ = match sound {
+            "yi" => "i",
+            "yin" => "in",
+            "ying" => "ing",
+            "yu" => "v",
+            "yue" => "ve",
+            "yuan" => "van",
+            "yun" => "vn",
+            "yong" => "iong",
+            "you" => "iou",
+            _ => return ("", format!("i{}", rest)),
+        };
+        return ("", final_.to_string());
+    }
+    if let Some(rest) = sound.strip_prefix('w') {
+        let final_ = if sound == "wu" { "u".to_string() } else { format!("u{}", rest) };
+        return ("", final_);
+    }
+    let (initial, rest) = split_initial(sound, PINYIN_INITIALS);
+    let final_ = match (initial, rest) {
+        ("zh", "i") | ("ch", "i") | ("sh", "i") | ("r", "i") | ("z", "i") | ("c", "i") | ("s", "i") => String::new(),
+        ("j", _) | ("q", _) | ("x", _) if rest.starts_with('u') => format!("v{}", &rest[1..]),
+        (_, "iu") => "iou".to_string(),
+        (_, "ui") => "uei".to_string(),
+        (_, "un") => "uen".to_string(),
+        _ => rest.to_string(),
+    };
+    (initial, final_)
+}
+
+const ZHUYIN_INITIALS: &[(&str, &str)] = &[
+    ("b", "ㄅ"), ("p", "ㄆ"), ("m", "ㄇ"), ("f", "ㄈ"), ("d", "ㄉ"), ("t", "ㄊ"), ("n", "ㄋ"),
+    ("l", "ㄌ"), ("g", "ㄍ"), ("k", "ㄎ"), ("h", "ㄏ"), ("j", "ㄐ"), ("q", "ㄑ"), ("x", "ㄒ"),
+    ("zh", "ㄓ"), ("ch", "ㄔ"), ("sh", "ㄕ"), ("r", "ㄖ"), ("z", "ㄗ"), ("c", "ㄘ"), ("s", "ㄙ"),
+];
+
+const ZHUYIN_FINALS: &[(&str, &str)] = &[
+    ("a", "ㄚ"), ("o", "ㄛ"), ("e", "ㄜ"), ("ai", "ㄞ"), ("ei", "ㄟ"), ("ao", "ㄠ"), ("ou", "ㄡ"),
+    ("an", "ㄢ"), ("en", "ㄣ"), ("ang", "ㄤ"), ("eng", "ㄥ"), ("er", "ㄦ"), ("ong", "ㄨㄥ"),
+    ("i", "ㄧ"), ("ia", "ㄧㄚ"), ("io", "ㄧㄛ"), ("ie", "ㄧㄝ"), ("iao", "ㄧㄠ"), ("iou", "ㄧㄡ"),
+    ("ian", "ㄧㄢ"), ("in", "ㄧㄣ"), ("iang", "ㄧㄤ"), ("ing", "ㄧㄥ"), ("iong", "ㄩㄥ"),
+    ("u", "ㄨ"), ("ua", "ㄨㄚ"), ("uo", "ㄨㄛ"), ("uai", "ㄨㄞ"), ("uei", "ㄨㄟ"), ("uan", "ㄨㄢ"),
+    ("uen", "ㄨㄣ"), ("uang", "ㄨㄤ"), ("ueng", "ㄨㄥ"), ("v", "ㄩ"), ("ve", "ㄩㄝ"), ("van", "ㄩㄢ"),
+    ("vn", "ㄩㄣ"),
+];
+
+/// Tone marks written after a Zhuyin syllable. The neutral tone is written before it.
+const ZHUYIN_TONES: &[(&str, char)] = &[("1", 'ˉ'), ("2", 'ˊ'), ("3", 'ˇ'), ("4", 'ˋ')];
+const ZHUYIN_NEUTRAL: char = '˙';
+
+fn pinyin_to_zhuyin(sound: &str, tone: &str) -> String {
+    let (initial, final_) = pinyin_parts(sound);
+    let mut zhuyin = String::new();
+    if tone == "5" {
+        zhuyin.push(ZHUYIN_NEUTRAL);
+    }
+    zhuyin.push_str(lookup(ZHUYIN_INITIALS, initial));
+    zhuyin.push_str(lookup(ZHUYIN_FINALS, &final_));
+    // the first tone is usually left unmarked
+    if let Some((_, mark)) = ZHUYIN_TONES.iter().find(|(t, _)| *t == tone && *t != "1") {
+        zhuyin.push(*mark);
+    }
+    zhuyin
+}
+
+lazy_static! {
+    /// The pinyin of every syllable, by its Zhuyin.
+    static ref ZHUYIN_SYLLABLES: HashMap<String, &'static str> = PINYIN_SYLLABLES
+        .iter()
+        .map(|sound| (pinyin_to_zhuyin(sound, "1"), *sound))
+        .collect();
+}
+
+/// Read Zhuyin syllables. Syllables with no tone marked are given `unmarked_tone`
+/// (usually the first tone, which is not normally marked).
+fn parse_zhuyin(text: &str, unmarked_tone: &str) -> Result<Vec<Syllable>, SearchError> {
+    let spellings = &*ZHUYIN_SYLLABLES;
+    let longest = spellings.keys().map(|spelling| spelling.chars().count()).max().unwrap_or(0);
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut syllables = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let neutral = chars[i] == ZHUYIN_NEUTRAL;
+        if neutral {
+            i += 1;
+        }
+        // take the longest syllable, so that e.g. ㄒㄧㄢ is not read as ㄒㄧ ㄢ
+        let (length, sound) = (1..=longest.min(chars.len() - i))
+            .rev()
+            .find_map(|length| {
+                let spelling: String = chars[i..i + length].iter().collect();
+                spellings.get(&spelling).map(|sound| (length, *sound))
+            })
+            .ok_or(SearchError::InvalidInput)?;
+        i += length;
+        let mut tone = if neutral { "5" } else { unmarked_tone };
+        if let Some((marked, _)) = chars.get(i).and_then(|c| ZHUYIN_TONES.iter().find(|(_, mark)| mark == c)) {
+            tone = *marked;
+            i += 1;
+        }
+        syllables.push(Syllable {
+            sound: sound.to_string(),
+            tone: tone.to_string(),
+        });
+    }
+    Ok(syllables)
+}
+
+const MANDARIN_IPA_INITIALS: &[(&str, &str)] = &[
+    ("b", "p"), ("p", "pʰ"), ("d", "t"), ("t", "tʰ"), ("g", "k"), ("k", "kʰ"), ("h", "x"),
+    ("j", "tɕ"), ("q", "tɕʰ"), ("x", "ɕ"), ("zh", "ʈʂ"), ("ch", "ʈʂʰ"), ("sh", "ʂ"), ("r", "ʐ"),
+    ("z", "ts"), ("c", "tsʰ"),
+];
+
+const MANDARIN_IPA_FINALS: &[(&str, &str)] = &[
+    ("a", "a"), ("o", "o"), ("e", "ɤ"), ("ai", "ai"), ("ei", "ei"), ("ao", "ɑu"), ("ou", "ou"),
+    ("an", "an"), ("en", "ən"), ("ang", "ɑŋ"), ("eng", "əŋ"), ("er", "aɚ"), ("ong", "ʊŋ"),
+    ("i", "i"), ("ia", "ja"), ("io", "jo"), ("ie", "jɛ"), ("iao", "jɑu"), ("iou", "jou"),
+    ("ian", "jɛn"), ("in", "in"), ("iang", "jɑŋ"), ("ing", "iŋ"), ("iong", "jʊŋ"), ("u", "u"),
+    ("ua", "wa"), ("uo", "wo"), ("uai", "wai"), ("uei", "wei"), ("uan", "wan"), ("uen", "wən"),
+    ("uang", "wɑŋ"), ("ueng", "wəŋ"), ("v", "y"), ("ve", "ɥɛ"), ("van", "ɥɛn"), ("vn", "yn"),
+];
+
+fn pinyin_to_ipa(sound: &str, tone: &str) -> String {
+    let (initial, final_) = pinyin_parts(sound);
+    let final_ = match (initial, &*final_) {
+        // the vowel of "zhi" and "zi"
+        ("zh", "") | ("ch", "") | ("sh", "") | ("r", "") => "ʐ̩",
+        (_, "") => "ɹ̩",
+        (_, final_) => lookup(MANDARIN_IPA_FINALS, final_),
+    };
+    let contour = match tone {
+        "1" => "˥",
+        "2" => "˧˥",
+        "3" => "˨˩˦",
+        "4" => "˥˩",
+        _ => "",
+    };
+    format!("{}{}{}", lookup(MANDARIN_IPA_INITIALS, initial), final_, contour)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use Romanization::*;
+
+    #[test]
+    fn yale() {
+        assert_eq!(convert("nei5 hou2", Jyutping, Yale).unwrap(), "néih hóu");
+        assert_eq!(convert("sik6 faan6", Jyutping, Yale).unwrap(), "sihk faahn");
+        assert_eq!(convert("jyu4 coeng3", Jyutping, Yale).unwrap(), "yùh cheung");
+        assert_eq!(convert("m4 goi1", Jyutping, Yale).unwrap(), "m\u{300}h gōi");
+        assert_eq!(convert("néih hóu", Yale, Jyutping).unwrap(), "nei5 hou2");
+        assert_eq!(convert("yùh-cheung", Yale, Jyutping).unwrap(), "jyu4 coeng3");
+        assert_eq!(convert("m\u{300}h gōi", Yale, Jyutping).unwrap(), "m4 goi1");
+        assert_eq!(convert("ǹgh ḿh ńgh", Yale, Jyutping).unwrap(), "ng4 m5 ng5");
+        assert_eq!(convert("Sīk", Yale, Jyutping).unwrap(), "sik1");
+        assert_eq!(convert("jung6 juk6", Jyutping, Yale).unwrap(), "yuhng yuhk");
+        assert_eq!(convert("yuhng yuhk", Yale, Jyutping).unwrap(), "jung6 juk6");
+        assert_eq!(convert("jyun4 jyut6", Jyutping, Yale).unwrap(), "yùhn yuht");
+        assert_eq!(convert("yùhn yuht", Yale, Jyutping).unwrap(), "jyun4 jyut6");
+    }
+    #[test]
+    fn cantonese_pinyin() {
+        assert_eq!(convert("zoek3 sik1 syu1", Jyutping, CantonesePinyin).unwrap(), "dzoek8 sik7 sy1");
+        assert_eq!(convert("dzoek8 sik7 sy1", CantonesePinyin, Jyutping).unwrap(), "zoek3 sik1 syu1");
+        assert_eq!(convert("caa4 seoi2", Jyutping, CantonesePinyin).unwrap(), "tsa4 soey2");
+    }
+    #[test]
+    fn pinyin_marks() {
+        assert_eq!(convert("ni3 hao3", Pinyin, PinyinMarks).unwrap(), "nǐ hǎo");
+        assert_eq!(convert("gou3 gui4 liu2 lu:4 ma5", Pinyin, PinyinMarks).unwrap(), "gǒu guì liú lǜ ma");
+        assert_eq!(convert("nǚ'ér", PinyinMarks, Pinyin).unwrap(), "nü3 er2");
+    }
+    #[test]
+    fn zhuyin() {
+        assert_eq!(convert("zhong1 guo2", Pinyin, Zhuyin).unwrap(), "ㄓㄨㄥ ㄍㄨㄛˊ");
+        assert_eq!(convert("ma1 ma5 ju2 shi4 you3 xian1", Pinyin, Zhuyin).unwrap(), "ㄇㄚ ˙ㄇㄚ ㄐㄩˊ ㄕˋ ㄧㄡˇ ㄒㄧㄢ");
+        assert_eq!(convert("ㄓㄨㄥ ㄍㄨㄛˊ", Zhuyin, Pinyin).unwrap(), "zhong1 guo2");
+        assert_eq!(convert("ㄒㄧㄢ˙ㄇㄚ", Zhuyin, PinyinMarks).unwrap(), "xiān ma");
+    }
+    #[test]
+    fn queries() {
+        assert_eq!(Yale.convert_query("néih ? hou").unwrap(), "nei5 ? hou");
+        assert_eq!(Yale.convert_query("yùh-cheung *").unwrap(), "jyu4 coeng *");
+        assert_eq!(Yale.convert_query("yuhng yung").unwrap(), "jung6 jung");
+        assert_eq!(Zhuyin.convert_query("ㄓㄨㄥ ㄍㄨㄛˊ").unwrap(), "zhong guo2");
+        assert_eq!(Zhuyin.convert_query("ㄓㄨㄥˉ ?").unwrap(), "zhong1 ?");
+        assert_eq!(Jyutping.convert_query("nei5 ?").unwrap(), "nei5 ?");
+        assert!(Yale.convert_query("xyz").is_err());
+    }
+    #[test]
+    fn ipa() {
+        assert_eq!(convert("sik6 faan6", Jyutping, CantoneseIpa).unwrap(), "sɪk̚˨ faːn˨");
+        assert_eq!(convert("ni3 hao3", Pinyin, MandarinIpa).unwrap(), "ni˨˩˦ xɑu˨˩˦");
+        assert!(convert("ni˨˩˦", MandarinIpa, Pinyin).is_err());
+    }
+    #[test]
+    fn invalid() {
+        assert!(convert("nei5", Jyutping, Pinyin).is_err());
+        assert!(convert("xyz", Jyutping, Yale).is_err());
+        assert!(convert("", Pinyin, Zhuyin).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Guessing which kinds of search a query is meant for, so that users do not
+//! need to choose between characters, jyutping, pinyin and English.
+
+use serde::Serialize;
+
+use super::syllables::segment;
+use super::{tokenise_pronunciation_query, PronunciationType, QueryToken};
+
+/// A kind of search.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryKind {
+    Characters,
+    Jyutping,
+    Pinyin,
+    English,
+    /// Characters together with jyutping or pinyin syllables, e.g. "垃 saap3".
+    Mixed,
+}
+
+impl QueryKind {
+    fn pronunciation_type(self) -> Option<PronunciationType> {
+        match self {
+            Self::Jyutping => Some(PronunciationType::Jyutping),
+            Self::Pinyin => Some(PronunciationType::Pinyin),
+            _ => None,
+        }
+    }
+}
+
+/// The kinds of search which a query could be meant for. A query may be valid
+/// romanization in both jyutping and pinyin (e.g. "si"), and romanization without
+/// tone numbers may also be English. Characters together with letters are searched
+/// as syllables in jyutping and pinyin.
+pub fn detect_query(query: &str) -> Vec<QueryKind> {
+    if query.chars().any(is_han) {
+        if query.chars().any(|c| c.is_alphabetic() && !is_han(c)) {
+            return vec![QueryKind::Mixed];
+        }
+        return vec![QueryKind::Characters];
+    }
+    let marked = has_tone_marks(query);
+    let numbered = query.chars().any(|c| c.is_ascii_digit());
+
+    let mut kinds = Vec::new();
+    for &kind in &[QueryKind::Jyutping, QueryKind::Pinyin] {
+        let pronunciation_type = kind.pronunciation_type().unwrap();
+        if marked && pronunciation_type != PronunciationType::Pinyin {
+            continue;
+        }
+        if is_romanization(query, pronunciation_type) {
+            kinds.push(kind);
+        }
+    }
+    if !marked && !numbered && query.chars().any(|c| c.is_ascii_alphabetic()) {
+        kinds.push(QueryKind::English);
+    }
+    kinds
+}
+
+/// Check whether a query has letters with tone marks or ü, which can only be pinyin.
+pub(crate) fn has_tone_marks(query: &str) -> bool {
+    query.chars().any(|c| c.is_alphabetic() && !c.is_ascii() && !is_han(c))
+}
+
+/// Check whether every sound in the query can be split into syllables with valid tones.
+fn is_romanization(query: &str, pronunciation_type: PronunciationType) -> bool {
+    let query = pronunciation_type.normalise(&query.to_lowercase());
+    let tokens = match tokenise_pronunciation_query(&query) {
+        Ok(("", tokens)) => tokens,
+        _ => return false,
+    };
+    let mut sounds = 0;
+    for token in &tokens {
+        if let QueryToken::Pronunciation { sound, tone } = token {
+            let tone = pronunciation_type.normalise_tone(tone);
+            if !tone.is_empty() && !pronunciation_type.tones().contains(&&*tone) {
+                return false;
+            }
+            if segment(pronunciation_type, sound, 1).is_empty() {
+                return false;
+            }
+            sounds += 1;
+        }
+    }
+    sounds > 0
+}
+
+/// Check whether a character is a Chinese character (in the CJK unified ideograph blocks).
+pub(crate) fn is_han(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' | '\u{20000}'..='\u{2FA1F}' | '〇'
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        use QueryKind::*;
+        assert_eq!(detect_query("垃圾"), vec![Characters]);
+        assert_eq!(detect_query("垃 saap3"), vec![Mixed]);
+        assert_eq!(detect_query("垃 ?"), vec![Characters]);
+        assert_eq!(detect_query("si"), vec![Jyutping, Pinyin, English]);
+        assert_eq!(detect_query("nǐ hǎo"), vec![Pinyin]);
+        assert_eq!(detect_query("gamjat"), vec![Jyutping, English]);
+        assert_eq!(detect_query("sik6 faan6"), vec![Jyutping]);
+        assert_eq!(detect_query("zhong1 guo2"), vec![Pinyin]);
+        assert_eq!(detect_query("rubbish bin"), vec![English]);
+        assert!(detect_query("? *").is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+//! Tokenising English definitions for reverse lookup.
+
+/// Common words which carry no meaning on their own. These are not indexed,
+/// since nearly every definition contains one of them.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "into", "is", "it",
+    "of", "on", "or", "sb", "sth", "so", "that", "the", "to", "with",
+];
+
+/// Split a single definition (sense) into search terms. Words are case-folded
+/// and stemmed, and stop words are removed. Text in brackets such as
+/// "(coll.)" is ignored.
+pub(crate) fn tokenise_english(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+
+    for c in text.chars().chain(std::iter::once(' ')) {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = std::cmp::max(depth - 1, 0),
+            _ => {}
+        }
+        if depth == 0 && c.is_ascii_alphanumeric() {
+            current.push(c.to_ascii_lowercase());
+        } else if !current.is_empty() {
+            if !STOP_WORDS.contains(&&*current) {
+                terms.push(stem(&current));
+            }
+            current.clear();
+        }
+    }
+    terms
+}
+
+/// Split a definitions string (with | separating each sense) into senses.
+pub(crate) fn split_senses(definitions: &str) -> impl Iterator<Item = &str> {
+    definitions.split('|').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// A light suffix-stripping stemmer so that e.g. "bins", "running" and
+/// "rubbished" match "bin", "run" and "rubbish". It is much less aggressive
+/// than a full Porter stemmer, as definitions are short.
+pub(crate) fn stem(word: &str) -> String {
+    let mut word = word.to_string();
+
+    if word.len() > 4 && word.ends_with("ies") {
+        word.truncate(word.len() - 3);
+        word.push('y');
+    } else if word.len() > 4 && (word.ends_with("sses") || word.ends_with("shes") || word.ends_with("ches") || word.ends_with("xes")) {
+        word.truncate(word.len() - 2);
+    } else if word.len() > 3
+        && word.ends_with('s')
+        && !word.ends_with("ss")
+        && !word.ends_with("us")
+        && !word.ends_with("is")
+    {
+        word.truncate(word.len() - 1);
+    }
+
+    for suffix in &["ing", "ed"] {
+        if word.len() > suffix.len() + 2 && word.ends_with(suffix) {
+            let stem_len = word.len() - suffix.len();
+            // the stem must contain a vowel (so that e.g. "bed" and "sing" are kept)
+            if !word[..stem_len].chars().any(is_vowel) {
+                continue;
+            }
+            // "ed" is part of words such as "speed" and "hundred"
+            if *suffix == "ed" && is_ed_word(&word[..stem_len]) {
+                continue;
+            }
+            word.truncate(stem_len);
+            let bytes = word.as_bytes();
+            let n = bytes.len();
+            let consonant = |i: usize| !is_vowel(bytes[i] as char);
+            if n >= 4 && bytes[n - 1] == bytes[n - 2] && consonant(n - 1) && !b"lsz".contains(&bytes[n - 1])
+                && !consonant(n - 3) && consonant(n - 4)
+            {
+                // undouble consonants after a short vowel: "running" -> "run", but "added" -> "add"
+                word.truncate(n - 1);
+            } else if is_short_syllable(&word) {
+                // restore a silent e: "making" -> "make"
+                word.push('e');
+            }
+            break;
+        }
+    }
+    word
+}
+
+/// Check whether a word ending in "ed" is not a past tense, from its stem: one ending
+/// in "e" ("speed", "need"), or in "r" after a consonant other than "r" ("hundred").
+fn is_ed_word(stem: &str) -> bool {
+    let bytes = stem.as_bytes();
+    let n = bytes.len();
+    bytes[n - 1] == b'e' || (bytes[n - 1] == b'r' && !is_vowel(bytes[n - 2] as char) && bytes[n - 2] != b'r')
+}
+
+/// Check whether a stem is a single syllable ending in a consonant after a single
+/// vowel (such as "mak" or "lik"), whose word ends in a silent e.
+fn is_short_syllable(stem: &str) -> bool {
+    let bytes = stem.as_bytes();
+    let n = bytes.len();
+    n >= 3
+        && !is_vowel(bytes[n - 1] as char)
+        && !b"wxy".contains(&bytes[n - 1])
+        && is_vowel(bytes[n - 2] as char)
+        && !bytes[..n - 2].iter().any(|b| is_vowel(*b as char))
+}
+
+fn is_vowel(c: char) -> bool {
+    "aeiouy".contains(c)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tokenise_rubbish_bin() {
+        assert_eq!(tokenise_english("Rubbish Bins"), vec!["rubbish", "bin"]);
+    }
+    #[test]
+    fn tokenise_stop_words_and_brackets() {
+        assert_eq!(
+            tokenise_english("(coll.) to be fond of sth"),
+            vec!["fond"]
+        );
+    }
+    #[test]
+    fn stem_suffixes() {
+        assert_eq!(stem("running"), "run");
+        assert_eq!(stem("cities"), "city");
+        assert_eq!(stem("boxes"), "box");
+        assert_eq!(stem("glass"), "glass");
+        assert_eq!(stem("walked"), "walk");
+        assert_eq!(stem("sing"), "sing");
+        assert_eq!(stem("bed"), "bed");
+        assert_eq!(stem("filled"), "fill");
+        assert_eq!(stem("speed"), "speed");
+        assert_eq!(stem("bleed"), "bleed");
+        assert_eq!(stem("need"), "need");
+        assert_eq!(stem("hundred"), "hundred");
+        assert_eq!(stem("stirred"), "stir");
+        assert_eq!(stem("added"), stem("add"));
+        assert_eq!(stem("liked"), stem("like"));
+        assert_eq!(stem("making"), stem("make"));
+        assert_eq!(stem("makes"), "make");
+        assert_eq!(stem("hoped"), "hope");
+        assert_eq!(stem("hopping"), "hop");
+    }
+    #[test]
+    fn senses() {
+        assert_eq!(
+            split_senses("|good|well| ").collect::<Vec<_>>(),
+            vec!["good", "well"]
+        );
+    }
+}
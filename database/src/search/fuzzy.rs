@@ -0,0 +1,150 @@
+//! Sound mergers, for matching pronunciations which many speakers (or learners)
+//! do not distinguish, e.g. "nei" and "lei" in Cantonese.
+
+use std::str::FromStr;
+
+use super::syllables::is_syllable;
+use super::{PronunciationType, SearchError};
+
+/// A group of sounds which are merged in fuzzy searches.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Merger {
+    /// The initials n- and l-.
+    NL,
+    /// An initial ng- and no initial.
+    NgInitial,
+    /// The final stops -p, -t and -k.
+    StopFinals,
+    /// The final nasals -n and -ng.
+    NasalFinals,
+    /// The initials z-, c-, s- and zh-, ch-, sh-.
+    Retroflex,
+}
+
+impl Merger {
+    /// The mergers used for a romanization when no others are given.
+    pub fn defaults(pronunciation_type: PronunciationType) -> &'static [Merger] {
+        match pronunciation_type {
+            PronunciationType::Jyutping => &[Self::NL, Self::NgInitial, Self::StopFinals, Self::NasalFinals],
+            PronunciationType::Pinyin => &[Self::Retroflex, Self::NasalFinals],
+        }
+    }
+    /// The sounds which this merger makes the same as `sound`.
+    fn alternatives(self, sound: &str) -> Vec<String> {
+        let mut alternatives = Vec::new();
+        match self {
+            Self::NL => {
+                if let Some(rest) = sound.strip_prefix('n').filter(|rest| !rest.starts_with('g')) {
+                    alternatives.push(format!("l{}", rest));
+                } else if let Some(rest) = sound.strip_prefix('l') {
+                    alternatives.push(format!("n{}", rest));
+                }
+            }
+            Self::NgInitial => {
+                if sound.starts_with("ng") && sound.len() > 2 {
+                    alternatives.push(sound[2..].to_string());
+                } else if sound.starts_with(|c: char| "aeiou".contains(c)) {
+                    alternatives.push(format!("ng{}", sound));
+                }
+            }
+            Self::StopFinals => {
+                if sound.len() > 1 && sound.ends_with(|c: char| "ptk".contains(c)) {
+                    let rest = &sound[..sound.len() - 1];
+                    for stop in &["p", "t", "k"] {
+                        if !sound.ends_with(stop) {
+                            alternatives.push(format!("{}{}", rest, stop));
+                        }
+                    }
+                }
+            }
+            Self::NasalFinals => {
+                // a syllable of only "ng" (in jyutping) is left alone
+                if sound.ends_with("ng") && sound.len() > 2 {
+                    alternatives.push(sound[..sound.len() - 1].to_string());
+                } else if sound.ends_with('n') && sound.len() > 1 {
+                    alternatives.push(format!("{}g", sound));
+                }
+            }
+            Self::Retroflex => {
+                for (plain, retroflex) in &[("z", "zh"), ("c", "ch"), ("s", "sh")] {
+                    if sound.starts_with(retroflex) {
+                        alternatives.push(format!("{}{}", plain, &sound[2..]));
+                    } else if sound.starts_with(plain) {
+                        alternatives.push(format!("{}{}", retroflex, &sound[1..]));
+                    }
+                }
+            }
+        }
+        alternatives
+    }
+}
+
+impl FromStr for Merger {
+    type Err = SearchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "n-l" => Ok(Self::NL),
+            "ng" => Ok(Self::NgInitial),
+            "stops" => Ok(Self::StopFinals),
+            "nasals" => Ok(Self::NasalFinals),
+            "retroflex" => Ok(Self::Retroflex),
+            _ => Err(SearchError::InvalidInput),
+        }
+    }
+}
+
+/// All the sounds which `sound` matches under the mergers, starting with `sound` itself.
+/// Only valid syllables are included.
+pub(crate) fn fuzzy_sounds(pronunciation_type: PronunciationType, sound: &str, mergers: &[Merger]) -> Vec<String> {
+    let mut sounds = vec![sound.to_string()];
+    // apply the mergers in turn, so that they combine (e.g. "nok" -> "lot")
+    for merger in mergers {
+        let mut merged = Vec::new();
+        for sound in &sounds {
+            for alternative in merger.alternatives(sound) {
+                if !sounds.contains(&alternative) && !merged.contains(&alternative) {
+                    merged.push(alternative);
+                }
+            }
+        }
+        sounds.extend(merged);
+    }
+    sounds.retain(|s| s == sound || is_syllable(pronunciation_type, s));
+    sounds
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn jyutping_mergers() {
+        let sounds = fuzzy_sounds(PronunciationType::Jyutping, "nok", &[Merger::NL, Merger::StopFinals]);
+        // "op" is not a jyutping final
+        assert_eq!(sounds, vec!["nok", "lok", "not", "lot"]);
+        assert_eq!(
+            fuzzy_sounds(PronunciationType::Jyutping, "ngo", &[Merger::NgInitial]),
+            vec!["ngo", "o"]
+        );
+        assert_eq!(
+            fuzzy_sounds(PronunciationType::Jyutping, "ng", Merger::defaults(PronunciationType::Jyutping)),
+            vec!["ng"]
+        );
+    }
+    #[test]
+    fn pinyin_mergers() {
+        let sounds = fuzzy_sounds(PronunciationType::Pinyin, "zhen", Merger::defaults(PronunciationType::Pinyin));
+        assert_eq!(sounds, vec!["zhen", "zen", "zheng", "zeng"]);
+        // "sun" -> "shun" is a syllable, "sung" is not
+        assert_eq!(
+            fuzzy_sounds(PronunciationType::Pinyin, "sun", Merger::defaults(PronunciationType::Pinyin)),
+            vec!["sun", "shun"]
+        );
+    }
+    #[test]
+    fn parse() {
+        assert_eq!("n-l".parse::<Merger>().unwrap(), Merger::NL);
+        assert!("x".parse::<Merger>().is_err());
+    }
+}
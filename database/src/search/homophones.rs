@@ -0,0 +1,152 @@
+//! Finding words which sound like another word, for wordplay and for writing lyrics.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::fuzzy::fuzzy_sounds;
+use super::{paginate, DictSearch, Id, InternId, Merger, Page, PronunciationType, Score, SearchPage};
+
+/// How closely a word sounds like another, closest first.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Homophony {
+    /// The same syllables and tones.
+    Exact,
+    /// The same syllables with different tones.
+    DifferentTones,
+    /// The same syllables after merging sounds, with any tones.
+    Fuzzy,
+}
+
+/// A word which sounds like another.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+pub struct Homophone {
+    pub id: Id,
+    pub score: Score,
+    pub kind: Homophony,
+}
+
+impl DictSearch {
+    /// Find the words with a pronunciation sounding like one of a word's pronunciations
+    /// of a type, closest first and then best first. Words which only sound alike when
+    /// the sounds in `mergers` are treated as the same are included if any are given.
+    pub fn homophones(
+        &self,
+        word_id: Id,
+        pronunciation_type: PronunciationType,
+        mergers: &[Merger],
+        page: Page,
+    ) -> SearchPage<Homophone> {
+        let mut best: HashMap<Id, (Homophony, Score)> = HashMap::new();
+        let pronunciation_ids = self.word_pronunciations.get(&word_id).into_iter().flatten();
+        for syllable_ids in pronunciation_ids.filter_map(|id| self.pronunciation_map.get(&(pronunciation_type, *id))) {
+            for (id, kind) in self.sounding_like(syllable_ids, pronunciation_type, mergers) {
+                let other_word = self.pronunciation_words[&id];
+                if other_word == word_id {
+                    continue;
+                }
+                let score = self.score(other_word, true, syllable_ids.len());
+                let entry = best.entry(other_word).or_insert((kind, score));
+                if kind < entry.0 {
+                    *entry = (kind, score);
+                }
+            }
+        }
+        let results = best
+            .into_iter()
+            .map(|(id, (kind, score))| Homophone { id, score, kind })
+            .collect();
+        paginate(results, page, |a, b| {
+            (a.kind, Reverse(a.score), a.id).cmp(&(b.kind, Reverse(b.score), b.id))
+        })
+    }
+    /// The pronunciations sounding like a pronunciation, and how closely.
+    fn sounding_like(
+        &self,
+        syllable_ids: &[InternId],
+        pronunciation_type: PronunciationType,
+        mergers: &[Merger],
+    ) -> Vec<(Id, Homophony)> {
+        let syllables: Vec<_> = syllable_ids.iter().map(|id| &self.syllables[*id as usize]).collect();
+        let merged_sounds: Vec<Vec<String>> = syllables
+            .iter()
+            .map(|syllable| fuzzy_sounds(pronunciation_type, &syllable.sound, mergers))
+            .collect();
+
+        // Candidates have the (merged) sound of the syllable with the fewest pronunciations.
+        let mut broad_filter: Option<Vec<&Vec<Id>>> = None;
+        for sounds in &merged_sounds {
+            let postings = self.postings_for_syllable(sounds, "", pronunciation_type);
+            let count = |p: &Vec<&Vec<Id>>| p.iter().map(|ids| ids.len()).sum::<usize>();
+            if broad_filter.as_ref().is_none_or(|best| count(&postings) < count(best)) {
+                broad_filter = Some(postings);
+            }
+        }
+
+        let mut results = Vec::new();
+        for id in broad_filter.into_iter().flatten().flatten() {
+            let candidate = &self.pronunciation_map[&(pronunciation_type, *id)];
+            if candidate.len() != syllable_ids.len() {
+                continue;
+            }
+            let candidate_syllables = candidate.iter().map(|id| &self.syllables[*id as usize]);
+            let kind = if &**candidate == syllable_ids {
+                Homophony::Exact
+            } else if candidate_syllables.clone().zip(&syllables).all(|(a, b)| a.sound == b.sound) {
+                Homophony::DifferentTones
+            } else if !mergers.is_empty()
+                && candidate_syllables
+                    .zip(&merged_sounds)
+                    .all(|(a, sounds)| sounds.contains(&a.sound))
+            {
+                Homophony::Fuzzy
+            } else {
+                continue;
+            };
+            results.push((*id, kind));
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn homophones(search: &DictSearch, word_id: Id, mergers: &[Merger]) -> Vec<(Id, Homophony)> {
+        let page = search.homophones(word_id, PronunciationType::Jyutping, mergers, Page::default());
+        page.results.into_iter().map(|h| (h.id, h.kind)).collect()
+    }
+
+    #[test]
+    fn homophones_of_word() {
+        let mut search = DictSearch::new();
+        search.insert_pronunciation(1, 1, "si1 gei1", PronunciationType::Jyutping);
+        search.insert_pronunciation(2, 2, "si1 gei1", PronunciationType::Jyutping);
+        search.insert_pronunciation(3, 3, "si6 gei6", PronunciationType::Jyutping);
+        search.insert_pronunciation(4, 4, "si1 gei1 hok6", PronunciationType::Jyutping);
+        search.insert_pronunciation(5, 5, "si1 kei4", PronunciationType::Jyutping);
+        search.insert_pronunciation(6, 6, "nei5 hou2", PronunciationType::Jyutping);
+        search.insert_pronunciation(7, 7, "lei5 hou2", PronunciationType::Jyutping);
+        search.insert_pronunciation(8, 8, "lei4 hou2", PronunciationType::Jyutping);
+        search.insert_pronunciation(9, 2, "si1 gei1", PronunciationType::Pinyin);
+
+        assert_eq!(
+            homophones(&search, 1, &[]),
+            vec![(2, Homophony::Exact), (3, Homophony::DifferentTones)]
+        );
+        assert_eq!(homophones(&search, 6, &[]), vec![]);
+        assert_eq!(
+            homophones(&search, 6, &[Merger::NL]),
+            vec![(7, Homophony::Fuzzy), (8, Homophony::Fuzzy)]
+        );
+        assert_eq!(
+            homophones(&search, 7, &[Merger::NL]),
+            vec![(8, Homophony::DifferentTones), (6, Homophony::Fuzzy)]
+        );
+        // no pronunciation of the type
+        assert!(search.homophones(1, PronunciationType::Pinyin, &[], Page::default()).results.is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+//! Normalising pinyin into the numbered form used by the search index,
+//! e.g. "Nǐ hǎo" -> "ni3 hao3" and "lü4", "lu:4" -> "lv4".
+
+/// Convert pinyin to lowercase numbered pinyin, with ü written as v.
+///
+/// Tone marks are removed and the tone number is placed after the syllable
+/// containing the mark. Syllables without a tone mark or number are left without
+/// a tone. Numbered pinyin is only lowercased, so this can be applied to any pinyin.
+pub(crate) fn normalise_pinyin(text: &str) -> String {
+    let mut letters = Vec::new();
+    let mut chars = text.chars().flat_map(char::to_lowercase).peekable();
+
+    // separate the base letter from the tone mark
+    while let Some(c) = chars.next() {
+        let (mut base, mut tone) = split_tone_mark(c);
+        // combining diacritics (decomposed unicode)
+        while let Some(&next) = chars.peek() {
+            match (base, next) {
+                ('u', '\u{308}') => base = 'ü',
+                (_, '\u{304}') => tone = Some(1),
+                (_, '\u{301}') => tone = Some(2),
+                (_, '\u{30C}') => tone = Some(3),
+                (_, '\u{300}') => tone = Some(4),
+                _ => break,
+            }
+            chars.next();
+        }
+        // "u:" is the CEDICT spelling of ü
+        if base == 'u' && chars.peek() == Some(&':') {
+            chars.next();
+            base = 'ü';
+        }
+        if base == 'ü' {
+            base = 'v';
+        }
+        letters.push((base, tone));
+    }
+
+    let mut result = String::new();
+    let mut i = 0;
+    while i < letters.len() {
+        let (c, tone) = letters[i];
+        result.push(c);
+        i += 1;
+        if let Some(tone) = tone {
+            // find the end of the syllable, after the vowel with the tone mark
+            while i < letters.len() && is_vowel(letters[i].0) && letters[i].1.is_none() {
+                result.push(letters[i].0);
+                i += 1;
+            }
+            let next = |j: usize| letters.get(j).map(|l| l.0);
+            let starts_syllable = |j: usize| next(j).is_some_and(is_vowel);
+            if next(i) == Some('n') && next(i + 1) == Some('g') && !starts_syllable(i + 2) {
+                result.push_str("ng");
+                i += 2;
+            } else if (next(i) == Some('n') || next(i) == Some('r')) && !starts_syllable(i + 1) {
+                result.push(letters[i].0);
+                i += 1;
+            }
+            result.push_str(&tone.to_string());
+        }
+    }
+    result
+}
+
+fn is_vowel(c: char) -> bool {
+    "aeiouv".contains(c)
+}
+
+/// Each vowel with the pinyin tone marks: macron, acute, caron and grave.
+pub(crate) const TONE_MARKS: [(char, [char; 4]); 6] = [
+    ('a', ['ā', 'á', 'ǎ', 'à']),
+    ('e', ['ē', 'é', 'ě', 'è']),
+    ('i', ['ī', 'í', 'ǐ', 'ì']),
+    ('o', ['ō', 'ó', 'ǒ', 'ò']),
+    ('u', ['ū', 'ú', 'ǔ', 'ù']),
+    ('ü', ['ǖ', 'ǘ', 'ǚ', 'ǜ']),
+];
+
+/// Split a (lowercase) letter with a tone mark into the letter and the tone.
+pub(crate) fn split_tone_mark(c: char) -> (char, Option<u8>) {
+    for (base, marked) in TONE_MARKS.iter() {
+        if let Some(i) = marked.iter().position(|m| *m == c) {
+            return (*base, Some(i as u8 + 1));
+        }
+    }
+    (c, None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::normalise_pinyin;
+
+    #[test]
+    fn tone_marks() {
+        assert_eq!(normalise_pinyin("nǐ hǎo"), "ni3 hao3");
+        assert_eq!(normalise_pinyin("Nǐhǎo"), "ni3hao3");
+        assert_eq!(normalise_pinyin("zhōngguó"), "zhong1guo2");
+        assert_eq!(normalise_pinyin("xī'ān"), "xi1'an1");
+        assert_eq!(normalise_pinyin("shuǐjiǎo"), "shui3jiao3");
+        // the apostrophe separates syllables which would otherwise be read as "tia1nan1"
+        assert_eq!(normalise_pinyin("tiān'ān"), "tian1'an1");
+        assert_eq!(normalise_pinyin("māma"), "ma1ma");
+        assert_eq!(normalise_pinyin("nǚ'ér"), "nv3'er2");
+    }
+    #[test]
+    fn combining_tone_marks() {
+        assert_eq!(normalise_pinyin("la\u{300}"), "la4");
+        assert_eq!(normalise_pinyin("lu\u{308}\u{30C}"), "lv3");
+    }
+    #[test]
+    fn u_umlaut() {
+        assert_eq!(normalise_pinyin("lǜ"), "lv4");
+        assert_eq!(normalise_pinyin("lü4"), "lv4");
+        assert_eq!(normalise_pinyin("lu:4 se4"), "lv4 se4");
+        assert_eq!(normalise_pinyin("lv4"), "lv4");
+    }
+    #[test]
+    fn numbered() {
+        assert_eq!(normalise_pinyin("Xi1 an1"), "xi1 an1");
+    }
+}
@@ -0,0 +1,125 @@
+//! Finding characters by radical and stroke count, for characters which the user
+//! cannot pronounce.
+
+use serde::{Deserialize, Serialize};
+
+use super::DictSearch;
+
+/// The 214 Kangxi radicals, in order.
+const RADICALS: &str = "\
+    一丨丶丿乙亅\
+    二亠人儿入八冂冖冫几凵刀力勹匕匚匸十卜卩厂厶又\
+    口囗土士夂夊夕大女子宀寸小尢尸屮山巛工己巾干幺广廴廾弋弓彐彡彳\
+    心戈戶手支攴文斗斤方无日曰月木欠止歹殳毋比毛氏气水火爪父爻爿片牙牛犬\
+    玄玉瓜瓦甘生用田疋疒癶白皮皿目矛矢石示禸禾穴立\
+    竹米糸缶网羊羽老而耒耳聿肉臣自至臼舌舛舟艮色艸虍虫血行衣襾\
+    見角言谷豆豕豸貝赤走足身車辛辰辵邑酉釆里\
+    金長門阜隶隹雨靑非\
+    面革韋韭音頁風飛食首香\
+    馬骨高髟鬥鬯鬲鬼\
+    魚鳥鹵鹿麥麻\
+    黃黍黑黹\
+    黽鼎鼓鼠\
+    鼻齊\
+    齒\
+    龍龜\
+    龠";
+
+/// The number of the first radical with each stroke count, from 1 stroke.
+const FIRST_WITH_STROKES: [u8; 17] = [1, 7, 30, 61, 95, 118, 147, 167, 176, 187, 195, 201, 205, 209, 211, 212, 214];
+
+/// A Kangxi radical.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Radical {
+    /// From 1 to 214.
+    pub number: u8,
+    pub character: char,
+    pub strokes: u8,
+}
+
+/// All the radicals, in order.
+pub fn radicals() -> Vec<Radical> {
+    RADICALS
+        .chars()
+        .enumerate()
+        .map(|(i, character)| {
+            let number = i as u8 + 1;
+            let strokes = FIRST_WITH_STROKES.iter().filter(|first| **first <= number).count() as u8;
+            Radical {
+                number,
+                character,
+                strokes,
+            }
+        })
+        .collect()
+}
+
+/// The radical and stroke counts of a character.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrokeInfo {
+    pub radical: u8,
+    pub residual_strokes: i8,
+    pub total_strokes: u8,
+}
+
+impl DictSearch {
+    pub fn insert_stroke_info(&mut self, character: char, info: StrokeInfo) {
+        if let Some(old) = self.stroke_info.insert(character, info) {
+            if let Some(characters) = self.radical_map.get_mut(&old.radical) {
+                characters.retain(|(_, c)| *c != character);
+            }
+        }
+        // kept sorted by residual strokes
+        let characters = self.radical_map.entry(info.radical).or_default();
+        let key = (info.residual_strokes, character);
+        if let Err(i) = characters.binary_search(&key) {
+            characters.insert(i, key);
+        }
+    }
+    pub fn stroke_info(&self, character: char) -> Option<StrokeInfo> {
+        self.stroke_info.get(&character).cloned()
+    }
+    /// The characters with a radical, and with the given number of strokes besides
+    /// the radical if any, by the number of other strokes.
+    pub fn radical_characters(&self, radical: u8, residual_strokes: Option<i8>) -> Vec<(char, StrokeInfo)> {
+        self.radical_map
+            .get(&radical)
+            .into_iter()
+            .flatten()
+            .filter(|(strokes, _)| residual_strokes.is_none_or(|residual_strokes| *strokes == residual_strokes))
+            .map(|(_, character)| (*character, self.stroke_info[character]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn radical_list() {
+        let radicals = radicals();
+        assert_eq!(radicals.len(), 214);
+        assert_eq!(radicals[0], Radical { number: 1, character: '一', strokes: 1 });
+        assert_eq!(radicals[29], Radical { number: 30, character: '口', strokes: 3 });
+        assert_eq!(radicals[74], Radical { number: 75, character: '木', strokes: 4 });
+        assert_eq!(radicals[213], Radical { number: 214, character: '龠', strokes: 17 });
+    }
+    #[test]
+    fn radical_characters() {
+        let info = |radical, residual_strokes, total_strokes| StrokeInfo {
+            radical,
+            residual_strokes,
+            total_strokes,
+        };
+        let mut search = DictSearch::new();
+        search.insert_stroke_info('林', info(75, 4, 8));
+        search.insert_stroke_info('本', info(75, 1, 5));
+        search.insert_stroke_info('木', info(75, 0, 4));
+        search.insert_stroke_info('口', info(30, 0, 3));
+        let characters: Vec<char> = search.radical_characters(75, None).into_iter().map(|(c, _)| c).collect();
+        assert_eq!(characters, vec!['木', '本', '林']);
+        assert_eq!(search.radical_characters(75, Some(4)), vec![('林', info(75, 4, 8))]);
+        assert!(search.radical_characters(1, None).is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+//! Splitting Chinese text into the words of the dictionary, so that whole
+//! sentences can be looked up and annotated.
+
+use std::cmp::Reverse;
+
+use serde::Serialize;
+
+use super::detect::is_han;
+use super::{DictSearch, Id};
+
+/// The most characters of text which may be segmented at once.
+pub const MAX_SEGMENT_TEXT: usize = 5000;
+
+/// A piece of segmented text.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Segment {
+    pub text: String,
+    /// The words written as the text, best first. This is empty for text which
+    /// is not in the dictionary, such as punctuation.
+    pub word_ids: Vec<Id>,
+}
+
+/// How bad a way of segmenting the text up to a position is. Fields are compared in order.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+struct Cost {
+    // Chinese characters which are not part of a word
+    unknown: usize,
+    segments: usize,
+    // the total frequency of the words, negated
+    rarity: i64,
+}
+
+// the best way of segmenting the text up to a position, ending with a segment from `start`
+struct Step {
+    cost: Cost,
+    start: usize,
+    word_ids: Vec<Id>,
+}
+
+impl DictSearch {
+    /// Split text into words of the dictionary, written in simplified or traditional
+    /// characters. The segmentation covering the most characters with words is chosen,
+    /// then the one with the fewest (i.e. longest) words, then the most common words.
+    pub fn segment(&self, text: &str) -> Vec<Segment> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut steps: Vec<Option<Step>> = (0..=chars.len()).map(|_| None).collect();
+        steps[0] = Some(Step {
+            cost: Cost::default(),
+            start: 0,
+            word_ids: Vec::new(),
+        });
+
+        for start in 0..chars.len() {
+            let cost = match &steps[start] {
+                Some(step) => step.cost,
+                None => continue,
+            };
+
+            let mut candidates = Vec::new();
+            for (length, ids) in self.suggestions.prefixes(&chars[start..]) {
+                let word: String = chars[start..start + length].iter().collect();
+                // the trie also has romanization keys
+                let mut word_ids: Vec<Id> = ids.iter().cloned().filter(|id| self.written_as(*id, &word)).collect();
+                if word_ids.is_empty() {
+                    continue;
+                }
+                word_ids.sort_by_key(|id| Reverse(self.score(*id, true, length)));
+                let frequency = self.score(word_ids[0], true, length).frequency;
+                let next = Cost {
+                    segments: cost.segments + 1,
+                    rarity: cost.rarity - frequency as i64,
+                    ..cost
+                };
+                candidates.push((start + length, next, word_ids));
+            }
+            // Text which is not in the dictionary is split into single Chinese
+            // characters, and runs of other characters.
+            let length = chars[start..].iter().take_while(|c| !is_han(**c)).count().max(1);
+            let next = Cost {
+                unknown: cost.unknown + if is_han(chars[start]) { 1 } else { 0 },
+                segments: cost.segments + 1,
+                ..cost
+            };
+            candidates.push((start + length, next, Vec::new()));
+
+            for (end, cost, word_ids) in candidates {
+                if steps[end].as_ref().is_none_or(|step| cost < step.cost) {
+                    steps[end] = Some(Step { cost, start, word_ids });
+                }
+            }
+        }
+
+        let mut segments = Vec::new();
+        let mut end = chars.len();
+        while end > 0 {
+            let step = steps[end].take().unwrap();
+            segments.push(Segment {
+                text: chars[step.start..end].iter().collect(),
+                word_ids: step.word_ids,
+            });
+            end = step.start;
+        }
+        segments.reverse();
+        segments
+    }
+    /// Check whether a word is written as `text`, in traditional or simplified characters.
+    fn written_as(&self, id: Id, text: &str) -> bool {
+        match self.characters_map.get(&id) {
+            Some(characters) => {
+                &*characters.traditional == text || characters.simplified.as_deref() == Some(text)
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn texts(segments: &[Segment]) -> Vec<&str> {
+        segments.iter().map(|segment| &*segment.text).collect()
+    }
+
+    #[test]
+    fn segment() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "我", "我");
+        search.insert_characters(2, "我們", "我们");
+        search.insert_characters(3, "們", "们");
+        search.insert_characters(4, "是", "是");
+        search.insert_characters(5, "學生", "学生");
+        search.insert_characters(6, "學", "学");
+        search.insert_characters(7, "生", "生");
+        search.insert_characters(8, "學生", "学生");
+        search.set_frequency(8, 10);
+
+        let segments = search.segment("我們是學生。");
+        assert_eq!(texts(&segments), vec!["我們", "是", "學生", "。"]);
+        assert_eq!(segments[2].word_ids, vec![8, 5]);
+        assert!(segments[3].word_ids.is_empty());
+
+        let segments = search.segment("我们是学生 OK");
+        assert_eq!(texts(&segments), vec!["我们", "是", "学生", " OK"]);
+
+        let segments = search.segment("她是");
+        assert_eq!(texts(&segments), vec!["她", "是"]);
+        assert!(segments[0].word_ids.is_empty());
+        assert!(search.segment("").is_empty());
+    }
+}
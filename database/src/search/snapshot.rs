@@ -0,0 +1,152 @@
+//! Saving the search index to disk, so that the web server does not have to
+//! rebuild it from the database at every start.
+//!
+//! A snapshot is a header followed by the bincode-encoded `DictSearch`:
+//! the magic bytes, the format version, the import revision of the database
+//! the index was built from, a checksum of the encoded index and its length.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::DictSearch;
+
+const MAGIC: &[u8; 4] = b"DSNP";
+/// This must be increased whenever the layout of `DictSearch` changes, or what is
+/// indexed changes (e.g. how English terms are stemmed).
+const VERSION: u32 = 9;
+const HEADER_LEN: usize = 4 + 4 + 4 + 8 + 8;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The file is not a snapshot, or is corrupt.
+    Invalid,
+    /// The snapshot was written by a different version of the program.
+    Version(u32),
+    /// The database has been imported into since the snapshot was made.
+    Stale { revision: i32 },
+}
+
+impl Error for SnapshotError {}
+
+impl Display for SnapshotError {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Self::Io(e) => write!(fmt, "Could not read snapshot: {}", e),
+            Self::Invalid => write!(fmt, "Invalid snapshot"),
+            Self::Version(v) => write!(fmt, "Snapshot has version {}, expected {}", v, VERSION),
+            Self::Stale { revision } => write!(fmt, "Snapshot is of import revision {}", revision),
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Write a snapshot of the index, built from the database at `revision`. The file
+/// is replaced atomically, so a server starting at the same time never reads half of it.
+pub fn save_snapshot(search: &DictSearch, revision: i32, path: &Path) -> io::Result<()> {
+    let payload = bincode::serialize(search).map_err(io::Error::other)?;
+
+    // appended rather than replacing the extension, so that e.g. "index.tmp" is not overwritten
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+    let mut file = File::create(&temp_path)?;
+    file.write_all(MAGIC)?;
+    file.write_all(&VERSION.to_le_bytes())?;
+    file.write_all(&revision.to_le_bytes())?;
+    file.write_all(&checksum(&payload).to_le_bytes())?;
+    file.write_all(&(payload.len() as u64).to_le_bytes())?;
+    file.write_all(&payload)?;
+    file.sync_all()?;
+    fs::rename(temp_path, path)
+}
+
+/// Load a snapshot of the index, which must have been built from the database at `revision`.
+pub fn load_snapshot(path: &Path, revision: i32) -> Result<DictSearch, SnapshotError> {
+    // the index is decoded onto the heap, so the file is read whole rather than mapped
+    let bytes = fs::read(path)?;
+
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return Err(SnapshotError::Invalid);
+    }
+    let u32_at = |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+    let u64_at = |i: usize| (u32_at(i) as u64) | ((u32_at(i + 4) as u64) << 32);
+
+    let version = u32_at(4);
+    if version != VERSION {
+        return Err(SnapshotError::Version(version));
+    }
+    let snapshot_revision = u32_at(8) as i32;
+    if snapshot_revision != revision {
+        return Err(SnapshotError::Stale {
+            revision: snapshot_revision,
+        });
+    }
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() as u64 != u64_at(20) || checksum(payload) != u64_at(12) {
+        return Err(SnapshotError::Invalid);
+    }
+    bincode::deserialize(payload).map_err(|_| SnapshotError::Invalid)
+}
+
+/// 64-bit FNV-1a, to detect truncated or corrupt snapshots.
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::search::{Page, PronunciationType};
+
+    fn snapshot_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("dictionary-{}-{}.snapshot", name, std::process::id()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "垃圾", "垃圾");
+        search.insert_pronunciation(10, 1, "laap6 saap3", PronunciationType::Jyutping);
+        search.rank_suggestions();
+        let path = snapshot_path("round-trip");
+        save_snapshot(&search, 3, &path).unwrap();
+
+        let loaded = load_snapshot(&path, 3).unwrap();
+        let page = loaded.search_pronunciation("laap6", PronunciationType::Jyutping, Page::default()).unwrap();
+        assert_eq!(page.results[0].id, 1);
+        assert_eq!(loaded.suggest("垃", 10), vec![1]);
+        match load_snapshot(&path, 4) {
+            Err(SnapshotError::Stale { revision: 3 }) => {}
+            _ => panic!("snapshot should be stale"),
+        }
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn corrupt() {
+        let path = snapshot_path("corrupt");
+        save_snapshot(&DictSearch::new(), 0, &path).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&path, bytes).unwrap();
+        match load_snapshot(&path, 0) {
+            Err(SnapshotError::Invalid) => {}
+            _ => panic!("snapshot should be invalid"),
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
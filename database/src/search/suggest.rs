@@ -0,0 +1,148 @@
+//! A prefix tree for completing partially typed words and pronunciations.
+//! Each node keeps the best words below it, so completions are found by walking
+//! down the prefix only.
+
+use serde::{Deserialize, Serialize};
+
+use super::Id;
+
+#[derive(Default, Serialize, Deserialize)]
+struct Node {
+    // sorted by character
+    children: Vec<(char, usize)>,
+    // the words with a key ending at this node
+    ids: Vec<Id>,
+    // the best words with a key starting with this node's prefix, best first
+    top: Vec<Id>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Trie {
+    // the root is the first node. Children always come after their parent.
+    nodes: Vec<Node>,
+}
+
+impl Trie {
+    pub(crate) fn new() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+        }
+    }
+    /// Add a word to a key. It is only completed after `rank` is called again.
+    pub(crate) fn insert(&mut self, key: &str, id: Id) {
+        let mut node = 0;
+        for c in key.chars() {
+            node = match self.nodes[node].children.binary_search_by_key(&c, |(c, _)| *c) {
+                Ok(i) => self.nodes[node].children[i].1,
+                Err(i) => {
+                    let child = self.nodes.len();
+                    self.nodes.push(Node::default());
+                    self.nodes[node].children.insert(i, (c, child));
+                    child
+                }
+            };
+        }
+        let ids = &mut self.nodes[node].ids;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    /// Remove a word from a key. The word is also dropped from the completions of
+    /// the prefixes of the key which it has no other key below, but other words
+    /// only take its place after `rank`.
+    pub(crate) fn remove(&mut self, key: &str, id: Id) {
+        let mut path = vec![0];
+        for c in key.chars() {
+            let node = *path.last().unwrap();
+            match self.nodes[node].children.binary_search_by_key(&c, |(c, _)| *c) {
+                Ok(i) => path.push(self.nodes[node].children[i].1),
+                Err(_) => return,
+            }
+        }
+        self.nodes[*path.last().unwrap()].ids.retain(|other| *other != id);
+        for &node in path.iter().rev() {
+            let nodes = &self.nodes;
+            let remains = nodes[node].ids.contains(&id)
+                || nodes[node].children.iter().any(|&(_, child)| nodes[child].top.contains(&id));
+            if remains {
+                break;
+            }
+            self.nodes[node].top.retain(|other| *other != id);
+        }
+    }
+    /// Find the best `limit` words below each node. This must be called after
+    /// inserting keys, before completing. `better` orders words from best to worst.
+    pub(crate) fn rank(&mut self, limit: usize, better: impl Fn(&Id, &Id) -> std::cmp::Ordering) {
+        // children come after their parent, so they are ranked first
+        for node in (0..self.nodes.len()).rev() {
+            let mut top = self.nodes[node].ids.clone();
+            for &(_, child) in &self.nodes[node].children {
+                for id in &self.nodes[child].top {
+                    if !top.contains(id) {
+                        top.push(*id);
+                    }
+                }
+            }
+            top.sort_by(|a, b| better(a, b));
+            top.truncate(limit);
+            // most nodes are leaves with one word, so spare capacity adds up
+            top.shrink_to_fit();
+            self.nodes[node].top = top;
+        }
+    }
+    /// The words with a key which is a prefix of `text`, with the length of the key
+    /// in characters, shortest first.
+    pub(crate) fn prefixes(&self, text: &[char]) -> Vec<(usize, &[Id])> {
+        let mut prefixes = Vec::new();
+        let mut node = 0;
+        for (i, c) in text.iter().enumerate() {
+            match self.nodes[node].children.binary_search_by_key(c, |(c, _)| *c) {
+                Ok(j) => node = self.nodes[node].children[j].1,
+                Err(_) => break,
+            }
+            if !self.nodes[node].ids.is_empty() {
+                prefixes.push((i + 1, &*self.nodes[node].ids));
+            }
+        }
+        prefixes
+    }
+    /// The best words with a key starting with `prefix`, best first.
+    pub(crate) fn complete(&self, prefix: &str) -> &[Id] {
+        let mut node = 0;
+        for c in prefix.chars() {
+            match self.nodes[node].children.binary_search_by_key(&c, |(c, _)| *c) {
+                Ok(i) => node = self.nodes[node].children[i].1,
+                Err(_) => return &[],
+            }
+        }
+        &self.nodes[node].top
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn complete() {
+        let mut trie = Trie::new();
+        trie.insert("垃圾", 1);
+        trie.insert("垃圾桶", 2);
+        trie.insert("拉", 3);
+        trie.insert("垃圾桶", 2);
+        trie.rank(10, |a, b| b.cmp(a));
+        assert_eq!(trie.complete("垃"), &[2, 1]);
+        assert_eq!(trie.complete(""), &[3, 2, 1]);
+        assert!(trie.complete("垃桶").is_empty());
+        let text: Vec<char> = "垃圾桶蓋".chars().collect();
+        let lengths: Vec<usize> = trie.prefixes(&text).into_iter().map(|(length, _)| length).collect();
+        assert_eq!(lengths, vec![2, 3]);
+        trie.rank(1, |a, b| a.cmp(b));
+        assert_eq!(trie.complete("垃"), &[1]);
+
+        trie.remove("垃圾", 1);
+        assert!(trie.complete("垃").is_empty());
+        trie.rank(10, |a, b| a.cmp(b));
+        assert_eq!(trie.complete("垃"), &[2]);
+    }
+}
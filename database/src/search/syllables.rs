@@ -0,0 +1,155 @@
+//! Syllable inventories, used to split romanization written without spaces
+//! (e.g. "nihao" -> "ni hao", "gamjat" -> "gam jat") into syllables.
+
+use super::PronunciationType;
+
+/// The most ways of splitting a query which are searched.
+pub(crate) const MAX_SEGMENTATIONS: usize = 4;
+
+/// The length of the longest syllable in any inventory (e.g. "zhuang", "gwaang").
+const MAX_SYLLABLE_LEN: usize = 6;
+
+/// Toneless pinyin syllables, with ü written as v. This must be kept sorted.
+/// Syllables which are only written on their own (the interjections "m", "n" and
+/// "ng", and "r" for erhua) are left out so that e.g. "xian" is not split as "xia n".
+pub(crate) const PINYIN_SYLLABLES: &[&str] = &[
+    "a", "ai", "an", "ang", "ao", "ba", "bai", "ban", "bang", "bao", "bei", "ben", "beng", "bi",
+    "bian", "biao", "bie", "bin", "bing", "bo", "bu", "ca", "cai", "can", "cang", "cao", "ce",
+    "cen", "ceng", "cha", "chai", "chan", "chang", "chao", "che", "chen", "cheng", "chi", "chong",
+    "chou", "chu", "chua", "chuai", "chuan", "chuang", "chui", "chun", "chuo", "ci", "cong", "cou",
+    "cu", "cuan", "cui", "cun", "cuo", "da", "dai", "dan", "dang", "dao", "de", "dei", "den",
+    "deng", "di", "dia", "dian", "diao", "die", "ding", "diu", "dong", "dou", "du", "duan", "dui",
+    "dun", "duo", "e", "ei", "en", "eng", "er", "fa", "fan", "fang", "fei", "fen", "feng", "fo",
+    "fou", "fu", "ga", "gai", "gan", "gang", "gao", "ge", "gei", "gen", "geng", "gong", "gou", "gu",
+    "gua", "guai", "guan", "guang", "gui", "gun", "guo", "ha", "hai", "han", "hang", "hao", "he",
+    "hei", "hen", "heng", "hong", "hou", "hu", "hua", "huai", "huan", "huang", "hui",
+    "hun", "huo", "ji", "jia", "jian", "jiang", "jiao", "jie", "jin", "jing", "jiong", "jiu", "ju",
+    "juan", "jue", "jun", "ka", "kai", "kan", "kang", "kao", "ke", "kei", "ken", "keng", "kong",
+    "kou", "ku", "kua", "kuai", "kuan", "kuang", "kui", "kun", "kuo", "la", "lai", "lan", "lang",
+    "lao", "le", "lei", "leng", "li", "lia", "lian", "liang", "liao", "lie", "lin", "ling", "liu",
+    "lo", "long", "lou", "lu", "luan", "lun", "luo", "lv", "lve", "ma", "mai", "man", "mang",
+    "mao", "me", "mei", "men", "meng", "mi", "mian", "miao", "mie", "min", "ming", "miu", "mo",
+    "mou", "mu", "na", "nai", "nan", "nang", "nao", "ne", "nei", "nen", "neng", "ni",
+    "nian", "niang", "niao", "nie", "nin", "ning", "niu", "nong", "nou", "nu", "nuan", "nun", "nuo",
+    "nv", "nve", "o", "ou", "pa", "pai", "pan", "pang", "pao", "pei", "pen", "peng", "pi", "pian",
+    "piao", "pie", "pin", "ping", "po", "pou", "pu", "qi", "qia", "qian", "qiang", "qiao", "qie",
+    "qin", "qing", "qiong", "qiu", "qu", "quan", "que", "qun", "ran", "rang", "rao", "re",
+    "ren", "reng", "ri", "rong", "rou", "ru", "rua", "ruan", "rui", "run", "ruo", "sa", "sai",
+    "san", "sang", "sao", "se", "sen", "seng", "sha", "shai", "shan", "shang", "shao", "she",
+    "shei", "shen", "sheng", "shi", "shou", "shu", "shua", "shuai", "shuan", "shuang", "shui",
+    "shun", "shuo", "si", "song", "sou", "su", "suan", "sui", "sun", "suo", "ta", "tai", "tan",
+    "tang", "tao", "te", "teng", "ti", "tian", "tiao", "tie", "ting", "tong", "tou", "tu", "tuan",
+    "tui", "tun", "tuo", "wa", "wai", "wan", "wang", "wei", "wen", "weng", "wo", "wu", "xi", "xia",
+    "xian", "xiang", "xiao", "xie", "xin", "xing", "xiong", "xiu", "xu", "xuan", "xue", "xun", "ya",
+    "yan", "yang", "yao", "ye", "yi", "yin", "ying", "yo", "yong", "you", "yu", "yuan", "yue",
+    "yun", "za", "zai", "zan", "zang", "zao", "ze", "zei", "zen", "zeng", "zha", "zhai", "zhan",
+    "zhang", "zhao", "zhe", "zhei", "zhen", "zheng", "zhi", "zhong", "zhou", "zhu", "zhua", "zhuai",
+    "zhuan", "zhuang", "zhui", "zhun", "zhuo", "zi", "zong", "zou", "zu", "zuan", "zui", "zun",
+    "zuo",
+];
+
+pub(crate) const JYUTPING_INITIALS: &[&str] = &[
+    "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "ng", "h", "gw", "kw", "w", "z", "c", "s", "j",
+];
+
+const JYUTPING_FINALS: &[&str] = &[
+    "aa", "aai", "aau", "aam", "aan", "aang", "aap", "aat", "aak", "a", "ai", "au", "am", "an", "ang",
+    "ap", "at", "ak", "e", "ei", "eu", "em", "en", "eng", "ep", "et", "ek", "i", "iu", "im", "in",
+    "ing", "ip", "it", "ik", "o", "oi", "ou", "on", "ong", "ot", "ok", "oe", "oeng", "oek", "eoi",
+    "eon", "eot", "u", "ui", "un", "ung", "ut", "uk", "yu", "yun", "yut",
+];
+
+/// Check whether a (lowercase, toneless) sound is a single syllable.
+pub(crate) fn is_syllable(pronunciation_type: PronunciationType, sound: &str) -> bool {
+    match pronunciation_type {
+        PronunciationType::Pinyin => PINYIN_SYLLABLES.binary_search(&sound).is_ok(),
+        PronunciationType::Jyutping => is_jyutping_syllable(sound),
+    }
+}
+
+/// Jyutping syllables are any initial (or none) followed by a final. This accepts
+/// some combinations which do not occur in Cantonese, which is harmless for splitting.
+fn is_jyutping_syllable(sound: &str) -> bool {
+    // syllabic nasals
+    if ["m", "ng", "hm", "hng"].contains(&sound) {
+        return true;
+    }
+    std::iter::once("")
+        .chain(JYUTPING_INITIALS.iter().cloned())
+        .any(|initial| sound.starts_with(initial) && JYUTPING_FINALS.contains(&&sound[initial.len()..]))
+}
+
+/// Split a run of letters into syllables, returning at most `limit` ways of doing so.
+/// Longer syllables are tried first, so earlier splits tend to have fewer syllables
+/// (e.g. "xian" before "xi an"). Nothing is returned if the letters cannot be split.
+pub(crate) fn segment(pronunciation_type: PronunciationType, letters: &str, limit: usize) -> Vec<Vec<&str>> {
+    if !letters.is_ascii() {
+        return Vec::new();
+    }
+    let n = letters.len();
+    // whether the letters from each position to the end can be split into syllables
+    let mut splittable = vec![false; n + 1];
+    splittable[n] = true;
+    for start in (0..n).rev() {
+        splittable[start] = (start + 1..=n.min(start + MAX_SYLLABLE_LEN))
+            .any(|end| splittable[end] && is_syllable(pronunciation_type, &letters[start..end]));
+    }
+
+    let mut segmentations = Vec::new();
+    if splittable[0] {
+        split_from(pronunciation_type, letters, 0, &splittable, &mut Vec::new(), &mut segmentations, limit);
+    }
+    segmentations
+}
+
+fn split_from<'a>(
+    pronunciation_type: PronunciationType,
+    letters: &'a str,
+    start: usize,
+    splittable: &[bool],
+    current: &mut Vec<&'a str>,
+    segmentations: &mut Vec<Vec<&'a str>>,
+    limit: usize,
+) {
+    if start == letters.len() {
+        segmentations.push(current.clone());
+        return;
+    }
+    for end in (start + 1..=letters.len().min(start + MAX_SYLLABLE_LEN)).rev() {
+        if segmentations.len() >= limit {
+            return;
+        }
+        if splittable[end] && is_syllable(pronunciation_type, &letters[start..end]) {
+            current.push(&letters[start..end]);
+            split_from(pronunciation_type, letters, end, splittable, current, segmentations, limit);
+            current.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pinyin_inventory_sorted() {
+        assert!(PINYIN_SYLLABLES.windows(2).all(|w| w[0] < w[1]));
+    }
+    #[test]
+    fn pinyin_segments() {
+        let pinyin = |letters| segment(PronunciationType::Pinyin, letters, MAX_SEGMENTATIONS);
+        assert_eq!(pinyin("nihao")[0], vec!["ni", "hao"]);
+        assert_eq!(pinyin("xian"), vec![vec!["xian"], vec!["xi", "an"]]);
+        assert_eq!(pinyin("zhongguo")[0], vec!["zhong", "guo"]);
+        assert_eq!(pinyin("tiananmen")[0], vec!["tian", "an", "men"]);
+        assert!(pinyin("xyz").is_empty());
+    }
+    #[test]
+    fn jyutping_segments() {
+        let jyutping = |letters| segment(PronunciationType::Jyutping, letters, 1);
+        assert_eq!(jyutping("gamjat"), vec![vec!["gam", "jat"]]);
+        assert_eq!(jyutping("laapsaaptung"), vec![vec!["laap", "saap", "tung"]]);
+        assert_eq!(jyutping("m"), vec![vec!["m"]]);
+        assert!(jyutping("s").is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+//! Variant characters, which are written differently but are used interchangeably
+//! (e.g. 裏 and 裡, or 群 and 羣), so that searching for one finds words written with
+//! the other.
+
+use std::borrow::Cow;
+
+use super::{DictSearch, Id, MatchOptions, Script};
+
+impl DictSearch {
+    /// Record that two characters are variants of each other.
+    pub fn insert_variant(&mut self, a: char, b: char) {
+        if a == b {
+            return;
+        }
+        for (character, variant) in &[(a, b), (b, a)] {
+            let variants = self.variants.entry(*character).or_default();
+            if let Err(i) = variants.binary_search(variant) {
+                variants.insert(i, *variant);
+            }
+        }
+    }
+    /// Check whether a character of a query matches a character of a word, or a variant of it.
+    fn same_character(&self, query_char: char, word_char: char, options: MatchOptions) -> bool {
+        query_char == word_char
+            || (!options.strict
+                && self
+                    .variants
+                    .get(&query_char)
+                    .is_some_and(|variants| variants.binary_search(&word_char).is_ok()))
+    }
+    /// Check whether a character of a query matches a character of a word, given as
+    /// its traditional and simplified forms.
+    pub(super) fn characters_match(&self, query_char: char, (trad, simp): (char, char), options: MatchOptions) -> bool {
+        match options.script {
+            Script::Any => {
+                self.same_character(query_char, trad, options) || self.same_character(query_char, simp, options)
+            }
+            Script::Traditional => self.same_character(query_char, trad, options),
+            Script::Simplified => self.same_character(query_char, simp, options),
+        }
+    }
+    /// The character and (unless matching strictly) its variants.
+    fn equivalents(&self, character: char, options: MatchOptions) -> Vec<char> {
+        let mut equivalents = vec![character];
+        if !options.strict {
+            equivalents.extend(self.variants.get(&character).into_iter().flatten());
+        }
+        equivalents
+    }
+    /// The words containing a character or one of its variants, sorted.
+    pub(super) fn words_with_character(&self, character: char, options: MatchOptions) -> Vec<Id> {
+        let mut word_ids: Vec<Id> = self.positions_of(character, options).iter().map(|(id, _)| *id).collect();
+        // the positions of each word are together
+        word_ids.dedup();
+        word_ids
+    }
+    /// The positions of a character or one of its variants in words.
+    pub(super) fn positions_of(&self, character: char, options: MatchOptions) -> Cow<'_, [(Id, u16)]> {
+        let equivalents = self.equivalents(character, options);
+        union(equivalents.iter().filter_map(|c| self.character_positions.get(c)).collect())
+    }
+}
+
+/// Merge sorted lists, without copying if there is only one.
+fn union<T: Ord + Copy>(lists: Vec<&Vec<T>>) -> Cow<'_, [T]> {
+    match lists.len() {
+        0 => Cow::Owned(Vec::new()),
+        1 => Cow::Borrowed(lists[0]),
+        _ => {
+            let mut merged: Vec<T> = lists.into_iter().flatten().cloned().collect();
+            merged.sort();
+            merged.dedup();
+            Cow::Owned(merged)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{Page, Script, SearchPage};
+    use super::*;
+
+    fn ids(page: SearchPage) -> Vec<Id> {
+        page.results.into_iter().map(|r| r.id).collect()
+    }
+
+    #[test]
+    fn variants() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "裡面", "里面");
+        search.insert_characters(2, "裏頭", "里头");
+        search.insert_characters(3, "羣眾", "群众");
+        search.insert_variant('裏', '裡');
+        search.insert_variant('群', '羣');
+        let strict = MatchOptions {
+            strict: true,
+            script: Script::Any,
+        };
+
+        let search_ids = |query, options| ids(search.search_characters_with(query, options, Page::default()).unwrap());
+        assert_eq!(search_ids("裏", MatchOptions::default()), vec![1, 2]);
+        assert_eq!(search_ids("裡", MatchOptions::default()), vec![1, 2]);
+        assert_eq!(search_ids("裏", strict), vec![2]);
+        // simplified and traditional forms always match
+        assert_eq!(search_ids("里", strict), vec![1, 2]);
+        assert_eq!(search_ids("群眾", strict), vec![3]);
+        assert_eq!(search_ids("羣众", strict), vec![3]);
+
+        let containing = |query, options| {
+            ids(search.search_characters_containing(query, options, Page::default()).unwrap())
+        };
+        assert_eq!(containing("面", MatchOptions::default()), vec![1]);
+        assert_eq!(containing("裏面", MatchOptions::default()), vec![1]);
+        assert!(containing("裏面", strict).is_empty());
+    }
+    #[test]
+    fn script() {
+        let mut search = DictSearch::new();
+        search.insert_characters(1, "後天", "后天");
+        search.insert_characters(2, "后妃", "后妃");
+        let script = |script| MatchOptions { strict: false, script };
+
+        let search_ids = |query, options| ids(search.search_characters_with(query, options, Page::default()).unwrap());
+        assert_eq!(search_ids("后", MatchOptions::default()), vec![1, 2]);
+        assert_eq!(search_ids("后", script(Script::Traditional)), vec![2]);
+        assert_eq!(search_ids("后", script(Script::Simplified)), vec![1, 2]);
+        assert_eq!(search_ids("後", script(Script::Traditional)), vec![1]);
+        assert!(search_ids("後", script(Script::Simplified)).is_empty());
+
+        let containing = |query, options| {
+            ids(search.search_characters_containing(query, options, Page::default()).unwrap())
+        };
+        assert_eq!(containing("后", script(Script::Traditional)), vec![2]);
+        assert_eq!(containing("后天", script(Script::Simplified)), vec![1]);
+        assert!(containing("后天", script(Script::Traditional)).is_empty());
+    }
+}
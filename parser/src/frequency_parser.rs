@@ -0,0 +1,36 @@
+use nom::bytes::complete::is_not;
+use nom::character::complete::{digit1, space1};
+use nom::sequence::tuple;
+
+/// Parse a word frequency list, with a word and its count on each line
+/// separated by whitespace. Other columns after the count are ignored, as are
+/// lines which do not start with a word and a count (such as headers).
+pub fn parse_frequencies(src: &str) -> Vec<(String, i32)> {
+    let mut result = Vec::new();
+    for line in src.lines() {
+        let parsed: nom::IResult<&str, _> = tuple((is_not(" \t"), space1, digit1))(line.trim());
+        if let Ok((_, (word, _, count))) = parsed {
+            // counts from large corpora may not fit in an i32
+            let count = count.parse::<i64>().unwrap_or(i64::MAX);
+            result.push((word.to_string(), std::cmp::min(count, i32::MAX as i64) as i32));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_frequencies;
+    #[test]
+    fn test_list() {
+        let src = "Word\tWCount\n的\t1000\n垃圾桶 25 0.5\n\n好 99999999999";
+        assert_eq!(
+            vec![
+                ("的".to_string(), 1000),
+                ("垃圾桶".to_string(), 25),
+                ("好".to_string(), i32::MAX)
+            ],
+            parse_frequencies(src)
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use database::models::CharacterDecomposition;
+
+/// Parse Ideographic Description Sequences in the format of the CHISE and cjkvi-ids
+/// files, with a code point, the character and its sequences on each line separated
+/// by tabs, e.g. `U+6797\t林\t⿰木木`. Only the first sequence of each character is
+/// used. Source tags (`^⿰木木$(GTJKV)`) and references to components without a
+/// code point (`&CDP-8B7C;`) are removed, and characters which are not decomposed
+/// are skipped.
+pub fn parse_ids(src: &str) -> Vec<CharacterDecomposition> {
+    let mut result = Vec::new();
+    for line in src.lines() {
+        if !line.starts_with("U+") {
+            continue;
+        }
+        let mut fields = line.split('\t').skip(1);
+        let (character, sequence) = match (fields.next(), fields.next()) {
+            (Some(character), Some(sequence)) => (character, sequence),
+            _ => continue,
+        };
+        let decomposition = clean_sequence(sequence);
+        if decomposition.is_empty() || decomposition == character {
+            continue;
+        }
+        result.push(CharacterDecomposition {
+            character: character.to_string(),
+            decomposition,
+        });
+    }
+    result
+}
+
+fn clean_sequence(sequence: &str) -> String {
+    let sequence = sequence.trim_start_matches('^');
+    let sequence = match sequence.find('$') {
+        Some(i) => &sequence[..i],
+        None => sequence,
+    };
+    let mut result = String::new();
+    let mut in_reference = false;
+    for c in sequence.chars() {
+        match c {
+            '&' => in_reference = true,
+            ';' if in_reference => in_reference = false,
+            _ if !in_reference => result.push(c),
+            _ => (),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_ids() {
+        let src = ";; comment\n\
+            U+4E00\t一\t一\n\
+            U+6797\t林\t⿰木木\n\
+            U+674F\t杏\t^⿱木口$(GHJKTV)\t^⿱木口$(X)\n\
+            U+4E9E\t亞\t⿱&CDP-8B7C;一\n";
+        let decomposition = |character: &str, decomposition: &str| CharacterDecomposition {
+            character: character.to_string(),
+            decomposition: decomposition.to_string(),
+        };
+        assert_eq!(
+            vec![decomposition("林", "⿰木木"), decomposition("杏", "⿱木口"), decomposition("亞", "⿱一")],
+            parse_ids(src)
+        );
+    }
+}
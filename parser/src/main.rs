@@ -1,15 +1,36 @@
 mod cedict_parser;
+mod frequency_parser;
+mod ids_parser;
 mod types;
+mod unihan_parser;
 
 use structopt::StructOpt;
 
 use crate::types::Entry;
+use database::models::{CharacterDecomposition, CharacterInfo, CharacterVariant};
 use database::diesel;
 use database::DbConnection;
 use database::diesel::connection::Connection; // so we can do transactions
 use database::diesel::result::Error as DieselError;
 use database::diesel::prelude::*;
 
+/// Import SQL for the SQLite backend.
+#[cfg(not(feature = "postgres"))]
+mod scripts {
+    pub const CREATE_TEMP: &str = include_str!("scripts/sqlite/create_temp.sql");
+    pub const MOVE_TEMP_DATA: &str = include_str!("scripts/sqlite/move_temp_data.sql");
+    pub const INSERT_TEMP: &str = "insert into temp_data values (?,?,?,?,?,?)";
+    pub const INSERT_TEMP_FREQUENCY: &str = "insert into temp_frequency values (?,?)";
+}
+/// Import SQL for the PostgreSQL backend.
+#[cfg(feature = "postgres")]
+mod scripts {
+    pub const CREATE_TEMP: &str = include_str!("scripts/postgres/create_temp.sql");
+    pub const MOVE_TEMP_DATA: &str = include_str!("scripts/postgres/move_temp_data.sql");
+    pub const INSERT_TEMP: &str = "insert into temp_data values ($1,$2,$3,$4,$5,$6)";
+    pub const INSERT_TEMP_FREQUENCY: &str = "insert into temp_frequency values ($1,$2)";
+}
+
 #[derive(Debug)]
 enum ParserError {
     FileError,
@@ -20,13 +41,34 @@ enum ParserError {
 #[derive(StructOpt)]
 #[structopt(
     name = "dict-parser",
-    about = "Parses a modified version of CEDICT and CC-Canto files."
+    about = "Imports dictionary data into the database."
 )]
-struct Opts {
-    #[structopt(short = "i", long)]
-    in_file: String,
-    #[structopt(short = "d", long)]
-    dictionary_id: i32,
+enum Opts {
+    /// Parses a modified version of CEDICT and CC-Canto files.
+    Cedict {
+        #[structopt(short = "i", long)]
+        in_file: String,
+        #[structopt(short = "d", long)]
+        dictionary_id: i32,
+    },
+    /// Imports word frequencies, from a file with a word and its count on each line.
+    Frequency {
+        #[structopt(short = "i", long)]
+        in_file: String,
+    },
+    /// Imports the radicals, stroke counts and variants of characters from Unihan files
+    /// (Unihan_IRGSources.txt has the radicals and stroke counts, and Unihan_Variants.txt
+    /// the variants).
+    Unihan {
+        #[structopt(short = "i", long)]
+        in_file: Vec<String>,
+    },
+    /// Imports how characters are made of components, from Ideographic Description
+    /// Sequence files such as those of CHISE or cjkvi-ids.
+    Ids {
+        #[structopt(short = "i", long)]
+        in_file: Vec<String>,
+    },
 }
 
 fn main() {
@@ -41,14 +83,50 @@ fn parse() -> Result<(), ParserError> {
 
     let pool = database::create_db_pool();
 
-    let input = std::fs::read_to_string(opt.in_file).map_err(|_| ParserError::FileError)?;
-    println!("Parsing data");
-    let mut result = cedict_parser::parse_cedict(&input).map_err(|_| ParserError::CedictError)?;
+    match opt {
+        Opts::Cedict { in_file, dictionary_id } => {
+            let input = std::fs::read_to_string(in_file).map_err(|_| ParserError::FileError)?;
+            println!("Parsing data");
+            let mut result = cedict_parser::parse_cedict(&input).map_err(|_| ParserError::CedictError)?;
 
-    for entry in &mut result.1 {
-        entry.dictionary_id = opt.dictionary_id;
+            for entry in &mut result.1 {
+                entry.dictionary_id = dictionary_id;
+            }
+            insert_entries(&result.1, &pool.get_connection(), dictionary_id)?;
+        }
+        Opts::Frequency { in_file } => {
+            let input = std::fs::read_to_string(in_file).map_err(|_| ParserError::FileError)?;
+            println!("Parsing data");
+            let frequencies = frequency_parser::parse_frequencies(&input);
+
+            update_frequencies(&frequencies, &pool.get_connection())?;
+        }
+        Opts::Unihan { in_file } => {
+            let mut input = String::new();
+            for file in in_file {
+                input.push_str(&std::fs::read_to_string(file).map_err(|_| ParserError::FileError)?);
+                input.push('\n');
+            }
+            println!("Parsing data");
+            let characters = unihan_parser::parse_unihan(&input);
+            let variants = unihan_parser::parse_variants(&input);
+
+            insert_unihan(&characters, &variants, &pool.get_connection())?;
+        }
+        Opts::Ids { in_file } => {
+            let mut decompositions = Vec::new();
+            for file in in_file {
+                let input = std::fs::read_to_string(file).map_err(|_| ParserError::FileError)?;
+                println!("Parsing data");
+                decompositions.extend(ids_parser::parse_ids(&input));
+            }
+            // the first file has priority
+            let mut seen = std::collections::HashSet::new();
+            decompositions.retain(|decomposition| seen.insert(decomposition.character.clone()));
+
+            insert_decompositions(&decompositions, &pool.get_connection())?;
+        }
     }
-    insert_entries(&result.1, &pool.get_connection(), opt.dictionary_id)?;
 
     Ok(())
 }
@@ -65,7 +143,7 @@ fn insert_entries(entries: &Vec<Entry>, connection: &DbConnection, dict_id: i32)
 
         println!("Creating temporary table");
         // direct execution, no need for sql_query
-        connection.execute(include_str!("scripts/create_temp.sql"))?;
+        connection.execute(scripts::CREATE_TEMP)?;
         println!("Inserting data");
 
         let mut data = Vec::new();
@@ -78,9 +156,8 @@ fn insert_entries(entries: &Vec<Entry>, connection: &DbConnection, dict_id: i32)
             }
         }
 
-        let insert = "insert into temp_data values (?,?,?,?,?,?)".to_string();
         for record in &data {
-            diesel::sql_query(&insert).bind::<Text, _>(&record.0)
+            diesel::sql_query(scripts::INSERT_TEMP).bind::<Text, _>(&record.0)
                 .bind::<Text, _>(&record.1)
                 .bind::<Integer, _>(record.2)
                 .bind::<Text, _>(&record.3)
@@ -90,9 +167,76 @@ fn insert_entries(entries: &Vec<Entry>, connection: &DbConnection, dict_id: i32)
         }
 
         println!("Moving data from temporary table to main tables");
-        connection.batch_execute(include_str!("scripts/move_temp_data.sql"))?;
+        connection.batch_execute(scripts::MOVE_TEMP_DATA)?;
+        database::bump_import_revision(connection)?;
         Ok(())
     }).map_err(|e| ParserError::DbError(e))?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn update_frequencies(frequencies: &[(String, i32)], connection: &DbConnection) -> Result<(), ParserError> {
+    use diesel::sql_types::{Integer, Text};
+    use diesel::connection::SimpleConnection;
+
+    connection.transaction::<_, DieselError, _>(|| {
+        println!("Creating temporary table");
+        connection.batch_execute(include_str!("scripts/frequency/create_temp.sql"))?;
+        println!("Inserting data");
+
+        for (word, frequency) in frequencies {
+            diesel::sql_query(scripts::INSERT_TEMP_FREQUENCY).bind::<Text, _>(word)
+                .bind::<Integer, _>(frequency)
+                .execute(connection)?;
+        }
+
+        println!("Updating word frequencies");
+        connection.batch_execute(include_str!("scripts/frequency/update_frequency.sql"))?;
+        database::bump_import_revision(connection)?;
+        Ok(())
+    }).map_err(ParserError::DbError)?;
+
+    Ok(())
+}
+
+fn insert_unihan(characters: &[CharacterInfo], variants: &[CharacterVariant], connection: &DbConnection) -> Result<(), ParserError> {
+    use database::schema::{character_info, character_variant};
+
+    connection.transaction::<_, DieselError, _>(|| {
+        println!("Deleting character data");
+        diesel::delete(character_info::table).execute(connection)?;
+        diesel::delete(character_variant::table).execute(connection)?;
+        println!("Inserting data");
+
+        // stay under the limit on bound parameters in a query
+        for chunk in characters.chunks(500) {
+            diesel::insert_into(character_info::table).values(chunk).execute(connection)?;
+        }
+        for chunk in variants.chunks(500) {
+            diesel::insert_into(character_variant::table).values(chunk).execute(connection)?;
+        }
+        database::bump_import_revision(connection)?;
+        Ok(())
+    }).map_err(ParserError::DbError)?;
+
+    Ok(())
+}
+
+fn insert_decompositions(decompositions: &[CharacterDecomposition], connection: &DbConnection) -> Result<(), ParserError> {
+    use database::schema::character_decomposition;
+
+    connection.transaction::<_, DieselError, _>(|| {
+        println!("Deleting decompositions");
+        diesel::delete(character_decomposition::table).execute(connection)?;
+        println!("Inserting data");
+
+        // stay under the limit on bound parameters in a query
+        for chunk in decompositions.chunks(500) {
+            diesel::insert_into(character_decomposition::table).values(chunk).execute(connection)?;
+        }
+        database::bump_import_revision(connection)?;
+        Ok(())
+    }).map_err(|e| ParserError::DbError(e))?;
+
+    Ok(())
+}
@@ -0,0 +1,5 @@
+create temp table temp_frequency (
+    word text,
+    frequency integer
+);
+create index temp_frequency_word on temp_frequency (word);
\ No newline at end of file
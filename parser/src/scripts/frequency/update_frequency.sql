@@ -0,0 +1,14 @@
+-- a word's frequency is the count of its traditional or simplified form,
+-- whichever is higher
+update word set frequency = (
+    select max(temp_frequency.frequency) from temp_frequency
+    where temp_frequency.word = word.traditional
+        or temp_frequency.word = word.simplified
+)
+where exists (
+    select 1 from temp_frequency
+    where temp_frequency.word = word.traditional
+        or temp_frequency.word = word.simplified
+);
+
+drop table temp_frequency;
\ No newline at end of file
@@ -0,0 +1,8 @@
+create temp table temp_data (
+    traditional text,
+    simplified text,
+    dictionary_id integer,
+    definitions text,
+    pronunciation_type integer,
+    pronunciation text
+);
\ No newline at end of file
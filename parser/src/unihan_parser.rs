@@ -0,0 +1,140 @@
+use std::collections::{BTreeSet, HashMap};
+
+use database::models::{CharacterInfo, CharacterVariant};
+
+/// Parse the radicals and stroke counts of characters from the Unihan database, which
+/// has a code point, a field name and a value on each line separated by tabs, e.g.
+/// `U+6797\tkRSUnicode\t75.4`. The `kRSUnicode` and `kTotalStrokes` fields are used,
+/// taking the first value of each; characters without both are skipped.
+pub fn parse_unihan(src: &str) -> Vec<CharacterInfo> {
+    let mut radicals = HashMap::new();
+    let mut total_strokes = HashMap::new();
+    for (character, field, value) in unihan_fields(src) {
+        let value = value.split_whitespace().next().unwrap_or("");
+        match field {
+            "kRSUnicode" => {
+                if let Some(radical) = parse_radical_stroke(value) {
+                    radicals.insert(character, radical);
+                }
+            }
+            "kTotalStrokes" => {
+                if let Ok(strokes) = value.parse::<i32>() {
+                    total_strokes.insert(character, strokes);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut result: Vec<CharacterInfo> = radicals
+        .into_iter()
+        .filter_map(|(character, (radical, residual_strokes))| {
+            total_strokes.get(&character).map(|total_strokes| CharacterInfo {
+                character: character.to_string(),
+                radical,
+                residual_strokes,
+                total_strokes: *total_strokes,
+            })
+        })
+        .collect();
+    result.sort_by(|a, b| a.character.cmp(&b.character));
+    result
+}
+
+/// Parse pairs of characters which are used interchangeably, from the `kSemanticVariant`
+/// and `kZVariant` fields of the Unihan database (e.g. `U+88CF\tkZVariant\tU+88E1`).
+/// Each pair is returned in both orders.
+pub fn parse_variants(src: &str) -> Vec<CharacterVariant> {
+    let mut pairs = BTreeSet::new();
+    for (character, field, value) in unihan_fields(src) {
+        if field != "kSemanticVariant" && field != "kZVariant" {
+            continue;
+        }
+        // each variant may be followed by its sources, e.g. `U+88E1<kMatthews`
+        for variant in value.split_whitespace() {
+            let variant = variant.split('<').next().and_then(parse_code_point);
+            if let Some(variant) = variant.filter(|variant| *variant != character) {
+                pairs.insert((character, variant));
+                pairs.insert((variant, character));
+            }
+        }
+    }
+    pairs
+        .into_iter()
+        .map(|(character, variant)| CharacterVariant {
+            character: character.to_string(),
+            variant: variant.to_string(),
+        })
+        .collect()
+}
+
+/// The character, field name and value on each line of the Unihan database.
+fn unihan_fields(src: &str) -> impl Iterator<Item = (char, &str, &str)> {
+    src.lines().filter(|line| !line.starts_with('#')).filter_map(|line| {
+        let mut fields = line.split('\t');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some(code_point), Some(field), Some(value)) => Some((parse_code_point(code_point)?, field, value)),
+            _ => None,
+        }
+    })
+}
+
+fn parse_code_point(code_point: &str) -> Option<char> {
+    if !code_point.starts_with("U+") {
+        return None;
+    }
+    std::char::from_u32(u32::from_str_radix(&code_point[2..], 16).ok()?)
+}
+
+/// Parse a radical and residual stroke count such as "75.4". An apostrophe after the
+/// radical marks a simplified form of the radical, which has the same number.
+fn parse_radical_stroke(value: &str) -> Option<(i32, i32)> {
+    let mut parts = value.splitn(2, '.');
+    let radical = parts.next()?.trim_end_matches('\'').parse::<i32>().ok()?;
+    let residual_strokes = parts.next()?.parse::<i32>().ok()?;
+    if !(1..=214).contains(&radical) {
+        return None;
+    }
+    Some((radical, residual_strokes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_unihan() {
+        let src = "# Unihan_IRGSources.txt\n\
+            U+6797\tkRSUnicode\t75.4\n\
+            U+6797\tkTotalStrokes\t8\n\
+            U+4E2A\tkRSUnicode\t2.2 9.1\n\
+            U+4E2A\tkTotalStrokes\t3\n\
+            U+9F9F\tkRSUnicode\t213'.0\n\
+            U+9F9F\tkTotalStrokes\t7 16\n\
+            U+4E00\tkRSUnicode\t1.0\n\
+            \n";
+        let info = |character: &str, radical, residual_strokes, total_strokes| CharacterInfo {
+            character: character.to_string(),
+            radical,
+            residual_strokes,
+            total_strokes,
+        };
+        assert_eq!(
+            vec![info("个", 2, 2, 3), info("林", 75, 4, 8), info("龟", 213, 0, 7)],
+            parse_unihan(src)
+        );
+    }
+    #[test]
+    fn test_variants() {
+        let src = "U+88CF\tkZVariant\tU+88E1\n\
+            U+7FA4\tkSemanticVariant\tU+7FA3<kMatthews,kMeyerWempe U+7FA4\n\
+            U+88CF\tkTotalStrokes\t13\n";
+        let variant = |character: &str, variant: &str| CharacterVariant {
+            character: character.to_string(),
+            variant: variant.to_string(),
+        };
+        assert_eq!(
+            vec![variant("羣", "群"), variant("群", "羣"), variant("裏", "裡"), variant("裡", "裏")],
+            parse_variants(src)
+        );
+    }
+}
@@ -1,13 +1,13 @@
-use actix_web::{web, HttpResponse, get};
+use actix_web::{web, HttpRequest, HttpResponse, get, post};
 use crate::AppData;
 use crate::error::DictError;
-use database::search::PronunciationType;
-use database::schema::{word_pronunciation, word, word_entry};
+use database::search::{convert, radicals, Homophony, MatchOptions, Merger, Page, PronunciationType, QueryKind, Romanization, Script, SearchError, SearchResult, MAX_SEGMENT_TEXT, MAX_SUGGESTIONS};
+use database::schema::{word, word_entry, word_pronunciation};
 use database::diesel::prelude::*;
 use database::diesel::result::Error as DieselError;
 use actix_web::error::BlockingError;
 use database::models::{Word, WordEntry, WordPronunciation};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use database::DbConnection;
 
@@ -17,16 +17,120 @@ struct WordResult {
     traditional: String,
     entries: HashMap<i32, Vec<EntryResult>>,
 }
+/// A word found by `auto_search`, with the kinds of search which found it.
+#[derive(Serialize)]
+struct LabelledWord {
+    #[serde(flatten)]
+    word: Word,
+    kinds: Vec<QueryKind>,
+}
+/// A page of search results.
+#[derive(Serialize)]
+struct PageResult<T> {
+    /// The number of matches, including those in other pages.
+    total: usize,
+    results: Vec<T>,
+}
 #[derive(Serialize)]
 struct EntryResult {
     definitions: String,
     pronunciations: HashMap<i32, Vec<String>>,
 }
 
-#[get("/search/{type:jyutping|pinyin}/{query}")]
-pub(crate) async fn pronunciation_search(data: web::Data<AppData>, path: web::Path<(String, String)>) -> Result<HttpResponse, DictError> {
+#[derive(Deserialize)]
+pub(crate) struct PageOptions {
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+impl PageOptions {
+    fn page(&self) -> Page {
+        let default = Page::default();
+        Page {
+            offset: self.offset.unwrap_or(default.offset),
+            limit: self.limit.unwrap_or(default.limit),
+        }
+    }
+}
+
+/// The systems to write pronunciations in, instead of those they are stored in.
+#[derive(Deserialize, Clone, Copy)]
+pub(crate) struct DisplayOptions {
+    /// e.g. `yale`
+    cantonese: Option<Romanization>,
+    /// e.g. `pinyin-marks`
+    mandarin: Option<Romanization>,
+}
+impl DisplayOptions {
+    fn romanization(&self, pronunciation_type: i32) -> Option<Romanization> {
+        match PronunciationType::from_integer(pronunciation_type)? {
+            PronunciationType::Jyutping => self.cantonese,
+            PronunciationType::Pinyin => self.mandarin,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct PronunciationOptions {
+    /// `true` for the common mergers of the romanization, or a comma separated
+    /// list of mergers (e.g. `n-l,ng`).
+    fuzzy: Option<String>,
+}
+impl PronunciationOptions {
+    fn mergers(&self, pronunciation_type: PronunciationType) -> Result<Vec<Merger>, DictError> {
+        match self.fuzzy.as_ref().map(|s| &**s) {
+            None | Some("false") => Ok(Vec::new()),
+            Some("true") => Ok(Merger::defaults(pronunciation_type).to_vec()),
+            Some(list) => list.split(',')
+                .map(|merger| merger.parse())
+                .collect::<Result<Vec<Merger>, SearchError>>()
+                .map_err(DictError::Search),
+        }
+    }
+}
+
+/// Search by pronunciation. Queries in Yale and Zhuyin are converted to jyutping and pinyin.
+#[get("/search/{type:jyutping|pinyin|yale|zhuyin}/{query}")]
+pub(crate) async fn pronunciation_search(data: web::Data<AppData>, path: web::Path<(String, String)>, options: web::Query<PronunciationOptions>, page: web::Query<PageOptions>) -> Result<HttpResponse, DictError> {
+    let dict_search = data.dict_search.current();
+    let conn = data.database_pool.clone();
+
+    let romanization = match &*path.0 {
+        "jyutping" => Romanization::Jyutping,
+        "pinyin" => Romanization::Pinyin,
+        "yale" => Romanization::Yale,
+        "zhuyin" => Romanization::Zhuyin,
+        _ => unreachable!(),
+    };
+    let pronunciation_type = romanization.pronunciation_type();
+    let query = romanization.convert_query(&path.1).map_err(DictError::Search)?;
+
+    let mergers = options.mergers(pronunciation_type)?;
+
+    let (word_ids, total) = match dict_search.search_pronunciation_fuzzy(&query, pronunciation_type, &mergers, page.page()) {
+        Ok(r) => (result_ids(r.results), r.total),
+        Err(e) => return Err(DictError::Search(e)),
+    };
+
+    let db_results: Vec<_> = web::block(move || {
+        let connection = &conn.get_connection();
+
+        get_word_results(word_ids, connection)
+    }).await.map_err(|e| {
+        match e {
+            BlockingError::Canceled => DictError::Actix,
+            BlockingError::Error(e) => DictError::Database(e),
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(PageResult { total, results: db_results }))
+}
+
+
+/// Search with characters, syllables and wildcards at each position, e.g. `垃 saap3 ?`.
+#[get("/search/mixed/{type:jyutping|pinyin}/{query}")]
+pub(crate) async fn mixed_search(data: web::Data<AppData>, path: web::Path<(String, String)>, page: web::Query<PageOptions>) -> Result<HttpResponse, DictError> {
     let query = &*path.1;
-    let dict_search = &data.dict_search;
+    let dict_search = data.dict_search.current();
     let conn = data.database_pool.clone();
 
     let pronunciation_type = match &*path.0 {
@@ -35,22 +139,15 @@ pub(crate) async fn pronunciation_search(data: web::Data<AppData>, path: web::Pa
         _ => unreachable!(),
     };
 
-    let pronunciation_ids = match dict_search.search_pronunciation(query, pronunciation_type) {
-        Ok(r) => r,
+    let (word_ids, total) = match dict_search.search_mixed(query, pronunciation_type, page.page()) {
+        Ok(r) => (result_ids(r.results), r.total),
         Err(e) => return Err(DictError::Search(e)),
     };
 
     let db_results: Vec<_> = web::block(move || {
         let connection = &conn.get_connection();
 
-        let result: Vec<i32> = word::table.inner_join(word_entry::table)
-            .inner_join(word_pronunciation::table.on(
-                word_entry::entry_id.eq(word_pronunciation::entry_id)))
-            .filter(word_pronunciation::pronunciation_id.eq_any(pronunciation_ids))
-            .select(word::word_id)
-            .load(connection)?;
-
-        get_word_results(result, connection)
+        get_word_results(word_ids, connection)
     }).await.map_err(|e| {
         match e {
             BlockingError::Canceled => DictError::Actix,
@@ -58,20 +155,163 @@ pub(crate) async fn pronunciation_search(data: web::Data<AppData>, path: web::Pa
         }
     })?;
 
-    Ok(HttpResponse::Ok().json(db_results))
+    Ok(HttpResponse::Ok().json(PageResult { total, results: db_results }))
 }
 
 
+#[derive(Deserialize)]
+pub(crate) struct CharacterOptions {
+    /// Find words containing the query anywhere, instead of starting with it.
+    contains: Option<bool>,
+    /// Only match the characters of the query, not their variants (e.g. 裡 for 裏).
+    strict: Option<bool>,
+    /// `traditional` or `simplified` to only match words written with the query in that
+    /// script, or `any` (the default).
+    script: Option<Script>,
+}
+impl CharacterOptions {
+    fn match_options(&self) -> MatchOptions {
+        MatchOptions {
+            strict: self.strict.unwrap_or(false),
+            script: self.script.unwrap_or_default(),
+        }
+    }
+}
+
 #[get("/search/characters/{query}")]
-pub(crate) async fn character_search(data: web::Data<AppData>, path: web::Path<String>) -> Result<HttpResponse, DictError> {
+pub(crate) async fn character_search(data: web::Data<AppData>, path: web::Path<String>, page: web::Query<PageOptions>, options: web::Query<CharacterOptions>) -> Result<HttpResponse, DictError> {
     let query = &*path;
-    let dict_search = &data.dict_search;
+    let dict_search = data.dict_search.current();
     let conn = data.database_pool.clone();
 
-    let word_ids = match dict_search.search_characters(query) {
+    let results = if options.contains.unwrap_or(false) {
+        dict_search.search_characters_containing(query, options.match_options(), page.page())
+    } else {
+        dict_search.search_characters_with(query, options.match_options(), page.page())
+    };
+    let (word_ids, total) = match results {
+        Ok(r) => (result_ids(r.results), r.total),
+        Err(e) => return Err(DictError::Search(e)),
+    };
+
+    let db_results: Vec<_> = web::block(move || {
+        let connection = &conn.get_connection();
+
+        get_word_results(word_ids, connection)
+    }).await.map_err(|e| {
+        match e {
+            BlockingError::Canceled => DictError::Actix,
+            BlockingError::Error(e) => DictError::Database(e),
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(PageResult { total, results: db_results }))
+}
+
+/// Characters with the components of a query, and a page of the words containing them.
+#[derive(Serialize)]
+struct ComponentResult {
+    characters: Vec<char>,
+    total: usize,
+    results: Vec<Word>,
+}
+
+/// Search for characters containing all the components in the query, e.g. `木口`.
+#[get("/search/components/{query}")]
+pub(crate) async fn component_search(data: web::Data<AppData>, path: web::Path<String>, page: web::Query<PageOptions>) -> Result<HttpResponse, DictError> {
+    let dict_search = data.dict_search.current();
+    let conn = data.database_pool.clone();
+
+    let result = dict_search.search_components(&path, page.page()).map_err(DictError::Search)?;
+    let word_ids = result_ids(result.words.results);
+
+    let db_results: Vec<_> = web::block(move || {
+        let connection = &conn.get_connection();
+
+        get_word_results(word_ids, connection)
+    }).await.map_err(|e| {
+        match e {
+            BlockingError::Canceled => DictError::Actix,
+            BlockingError::Error(e) => DictError::Database(e),
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(ComponentResult {
+        characters: result.characters,
+        total: result.words.total,
+        results: db_results,
+    }))
+}
+
+/// Search as characters, jyutping, pinyin and/or English, depending on the query.
+#[get("/search/auto/{query}")]
+pub(crate) async fn auto_search(data: web::Data<AppData>, path: web::Path<String>, page: web::Query<PageOptions>) -> Result<HttpResponse, DictError> {
+    let query = &*path;
+    let dict_search = data.dict_search.current();
+    let conn = data.database_pool.clone();
+
+    let results = match dict_search.search_auto(query, page.page()) {
         Ok(r) => r,
         Err(e) => return Err(DictError::Search(e)),
     };
+    let total = results.total;
+    let word_ids: Vec<_> = results.results.iter().map(|r| r.id).collect();
+    let mut kinds: HashMap<_, _> = results.results.into_iter().map(|r| (r.id, r.kinds)).collect();
+
+    let db_results: Vec<_> = web::block(move || {
+        let connection = &conn.get_connection();
+
+        get_word_results(word_ids, connection)
+    }).await.map_err(|e| {
+        match e {
+            BlockingError::Canceled => DictError::Actix,
+            BlockingError::Error(e) => DictError::Database(e),
+        }
+    })?;
+
+    let labelled: Vec<_> = db_results.into_iter().map(|word| LabelledWord {
+        kinds: kinds.remove(&word.word_id).unwrap_or_default(),
+        word,
+    }).collect();
+    Ok(HttpResponse::Ok().json(PageResult { total, results: labelled }))
+}
+
+#[get("/search/english/{query}")]
+pub(crate) async fn english_search(data: web::Data<AppData>, path: web::Path<String>, page: web::Query<PageOptions>) -> Result<HttpResponse, DictError> {
+    let query = &*path;
+    let dict_search = data.dict_search.current();
+    let conn = data.database_pool.clone();
+
+    let (word_ids, total) = match dict_search.search_definitions(query, page.page()) {
+        Ok(r) => (result_ids(r.results), r.total),
+        Err(e) => return Err(DictError::Search(e)),
+    };
+
+    let db_results: Vec<_> = web::block(move || {
+        let connection = &conn.get_connection();
+
+        get_word_results(word_ids, connection)
+    }).await.map_err(|e| {
+        match e {
+            BlockingError::Canceled => DictError::Actix,
+            BlockingError::Error(e) => DictError::Database(e),
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(PageResult { total, results: db_results }))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SuggestOptions {
+    limit: Option<usize>,
+}
+
+/// Words completing partially typed characters or romanization, most frequent first.
+#[get("/suggest/{query}")]
+pub(crate) async fn suggest(data: web::Data<AppData>, path: web::Path<String>, options: web::Query<SuggestOptions>) -> Result<HttpResponse, DictError> {
+    let limit = options.limit.unwrap_or(MAX_SUGGESTIONS).min(MAX_SUGGESTIONS);
+    let word_ids = data.dict_search.current().suggest(&path, limit);
+    let conn = data.database_pool.clone();
 
     let db_results: Vec<_> = web::block(move || {
         let connection = &conn.get_connection();
@@ -87,13 +327,149 @@ pub(crate) async fn character_search(data: web::Data<AppData>, path: web::Path<S
     Ok(HttpResponse::Ok().json(db_results))
 }
 
+#[derive(Deserialize)]
+pub(crate) struct AnnotateRequest {
+    text: String,
+    /// The system to write pronunciations in (which also chooses the language), jyutping by default.
+    romanization: Option<Romanization>,
+}
+
+/// A segment of annotated text. The pronunciations and gloss are of the best word.
+#[derive(Serialize)]
+struct AnnotatedSegment {
+    text: String,
+    word_ids: Vec<i32>,
+    pronunciations: Vec<String>,
+    gloss: Option<String>,
+}
+
+/// Split text into words, with their pronunciations and a short definition, for reading.
+#[post("/annotate")]
+pub(crate) async fn annotate(data: web::Data<AppData>, request: web::Json<AnnotateRequest>) -> Result<HttpResponse, DictError> {
+    let romanization = request.romanization.unwrap_or(Romanization::Jyutping);
+    let pronunciation_type = romanization.pronunciation_type();
+    if request.text.chars().count() > MAX_SEGMENT_TEXT {
+        return Err(DictError::Search(SearchError::InvalidInput));
+    }
+    let segments = data.dict_search.current().segment(&request.text);
+    let mut word_ids: Vec<i32> = segments.iter().filter_map(|segment| segment.word_ids.first().cloned()).collect();
+    // the same word is often found several times in a text
+    word_ids.sort_unstable();
+    word_ids.dedup();
+    let conn = data.database_pool.clone();
+
+    let words = web::block(move || {
+        let connection = &conn.get_connection();
+
+        get_word_annotations(&word_ids, pronunciation_type, connection)
+    }).await.map_err(|e| {
+        match e {
+            BlockingError::Canceled => DictError::Actix,
+            BlockingError::Error(e) => DictError::Database(e),
+        }
+    })?;
+
+    let annotated: Vec<_> = segments.into_iter().map(|segment| {
+        let (pronunciations, gloss) = segment.word_ids.first()
+            .and_then(|id| words.get(id).cloned())
+            .unwrap_or_default();
+        let pronunciations = pronunciations.iter()
+            .map(|pronunciation| render_pronunciation(pronunciation, pronunciation_type, Some(romanization)))
+            .collect();
+        AnnotatedSegment { text: segment.text, word_ids: segment.word_ids, pronunciations, gloss }
+    }).collect();
+    Ok(HttpResponse::Ok().json(annotated))
+}
+
+#[derive(Serialize)]
+struct ConvertResult {
+    text: String,
+}
+
+/// Convert a pronunciation between two systems for the same language, e.g.
+/// `/convert/jyutping/yale/nei5 hou2`.
+#[get("/convert/{from}/{to}/{text}")]
+pub(crate) async fn convert_pronunciation(path: web::Path<(Romanization, Romanization, String)>) -> Result<HttpResponse, DictError> {
+    let text = convert(&path.2, path.0, path.1).map_err(DictError::Search)?;
+    Ok(HttpResponse::Ok().json(ConvertResult { text }))
+}
+
+/// The Kangxi radicals, with their numbers and stroke counts.
+#[get("/radicals")]
+pub(crate) async fn radical_list() -> Result<HttpResponse, DictError> {
+    Ok(HttpResponse::Ok().json(radicals()))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct RadicalOptions {
+    /// The number of strokes besides the radical.
+    strokes: Option<i8>,
+}
+
+#[derive(Serialize)]
+struct RadicalCharacter {
+    character: char,
+    residual_strokes: i8,
+    total_strokes: u8,
+}
+
+/// The characters with a radical (numbered from 1 to 214), by the number of other strokes.
+#[get("/radicals/{number}")]
+pub(crate) async fn radical_characters(data: web::Data<AppData>, path: web::Path<u8>, options: web::Query<RadicalOptions>) -> Result<HttpResponse, DictError> {
+    let radical = *path;
+    if !(1..=214).contains(&radical) {
+        return Err(DictError::Search(SearchError::InvalidInput));
+    }
+    let characters: Vec<_> = data.dict_search.current()
+        .radical_characters(radical, options.strokes)
+        .into_iter()
+        .map(|(character, info)| RadicalCharacter {
+            character,
+            residual_strokes: info.residual_strokes,
+            total_strokes: info.total_strokes,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(characters))
+}
+
+/// Search for words starting with a character.
+#[get("/search/starting/{character}")]
+pub(crate) async fn starting_search(data: web::Data<AppData>, path: web::Path<String>, page: web::Query<PageOptions>) -> Result<HttpResponse, DictError> {
+    let mut chars = path.chars();
+    let character = match (chars.next(), chars.next()) {
+        (Some(c), None) => c,
+        _ => return Err(DictError::Search(SearchError::InvalidInput)),
+    };
+    let dict_search = data.dict_search.current();
+    let conn = data.database_pool.clone();
+
+    let (word_ids, total) = match dict_search.search_starting_with(character, page.page()) {
+        Ok(r) => (result_ids(r.results), r.total),
+        Err(e) => return Err(DictError::Search(e)),
+    };
+
+    let db_results: Vec<_> = web::block(move || {
+        let connection = &conn.get_connection();
+
+        get_word_results(word_ids, connection)
+    }).await.map_err(|e| {
+        match e {
+            BlockingError::Canceled => DictError::Actix,
+            BlockingError::Error(e) => DictError::Database(e),
+        }
+    })?;
+
+    Ok(HttpResponse::Ok().json(PageResult { total, results: db_results }))
+}
+
 #[get("/word/{word_id}")]
-pub(crate) async fn single_word(data: web::Data<AppData>, path: web::Path<i32>) -> Result<HttpResponse, DictError> {
+pub(crate) async fn single_word(data: web::Data<AppData>, path: web::Path<i32>, options: web::Query<DisplayOptions>) -> Result<HttpResponse, DictError> {
     let conn = data.database_pool.clone();
+    let options = options.into_inner();
     let db_result = web::block(move || {
         let connection = &conn.get_connection();
 
-        get_word_long_result(*path, connection)
+        get_word_long_result(*path, options, connection)
     }).await.map_err(|e| {
         match e {
             BlockingError::Canceled => DictError::Actix,
@@ -103,13 +479,121 @@ pub(crate) async fn single_word(data: web::Data<AppData>, path: web::Path<i32>)
     Ok(HttpResponse::Ok().json(db_result))
 }
 
+#[derive(Deserialize)]
+pub(crate) struct HomophoneOptions {
+    /// `jyutping` (the default) or `pinyin`.
+    #[serde(rename = "type")]
+    pronunciation_type: Option<String>,
+}
+
+/// A word which sounds like another, with how closely.
+#[derive(Serialize)]
+struct HomophoneWord {
+    #[serde(flatten)]
+    word: Word,
+    kind: Homophony,
+}
+
+/// Words which sound like a word in Cantonese or Mandarin: those with the same syllables
+/// and tones, then the same syllables with other tones, then (with `fuzzy`, as for
+/// pronunciation searches) those which only sound alike when sounds are merged.
+#[get("/word/{word_id}/homophones")]
+pub(crate) async fn homophones(data: web::Data<AppData>, path: web::Path<i32>, options: web::Query<HomophoneOptions>, fuzzy: web::Query<PronunciationOptions>, page: web::Query<PageOptions>) -> Result<HttpResponse, DictError> {
+    let pronunciation_type = match options.pronunciation_type.as_ref().map(|s| &**s) {
+        None | Some("jyutping") => PronunciationType::Jyutping,
+        Some("pinyin") => PronunciationType::Pinyin,
+        Some(_) => return Err(DictError::Search(SearchError::InvalidInput)),
+    };
+    let mergers = fuzzy.mergers(pronunciation_type)?;
+    let found = data.dict_search.current().homophones(*path, pronunciation_type, &mergers, page.page());
+    let total = found.total;
+    let word_ids: Vec<_> = found.results.iter().map(|homophone| homophone.id).collect();
+    let kinds: HashMap<_, _> = found.results.iter().map(|homophone| (homophone.id, homophone.kind)).collect();
+    let conn = data.database_pool.clone();
+
+    let db_results: Vec<_> = web::block(move || {
+        let connection = &conn.get_connection();
+
+        get_word_results(word_ids, connection)
+    }).await.map_err(|e| {
+        match e {
+            BlockingError::Canceled => DictError::Actix,
+            BlockingError::Error(e) => DictError::Database(e),
+        }
+    })?;
+
+    let results: Vec<_> = db_results.into_iter().map(|word| HomophoneWord {
+        kind: kinds[&word.word_id],
+        word,
+    }).collect();
+    Ok(HttpResponse::Ok().json(PageResult { total, results }))
+}
+
+/// The word ids of search results, best match first.
+fn result_ids(results: Vec<SearchResult>) -> Vec<i32> {
+    results.into_iter().map(|r| r.id).collect()
+}
+
+/// Fetch words, in the same order as `word_ids`.
 fn get_word_results(word_ids: Vec<i32>, connection: &DbConnection) -> Result<Vec<Word>, DieselError> {
-    word::table.filter(word::word_id.eq_any(word_ids))
-        .load::<Word>(connection)
+    let mut words = word::table.filter(word::word_id.eq_any(&word_ids))
+        .load::<Word>(connection)?;
+
+    // the database does not preserve the order of the ids
+    let mut positions = HashMap::new();
+    for (i, word_id) in word_ids.iter().enumerate() {
+        positions.entry(*word_id).or_insert(i);
+    }
+    words.sort_by_key(|w| positions.get(&w.word_id).cloned());
+    Ok(words)
+}
+
+
+/// The most ids to look up in one query, as SQLite limits the number of bound variables.
+const MAX_QUERY_IDS: usize = 500;
+
+/// The pronunciations of each word in one romanization, and the first sense of its first entry.
+fn get_word_annotations(word_ids: &[i32], pronunciation_type: PronunciationType, connection: &DbConnection) -> Result<HashMap<i32, (Vec<String>, Option<String>)>, DieselError> {
+    let mut entries = Vec::new();
+    for chunk in word_ids.chunks(MAX_QUERY_IDS) {
+        entries.extend(word_entry::table.filter(word_entry::word_id.eq_any(chunk))
+            .load::<WordEntry>(connection)?);
+    }
+    entries.sort_by_key(|entry| entry.entry_id);
+    let mut pronunciations = Vec::new();
+    for chunk in entries.chunks(MAX_QUERY_IDS) {
+        pronunciations.extend(WordPronunciation::belonging_to(chunk)
+            .filter(word_pronunciation::pronunciation_type.eq(pronunciation_type.to_integer()))
+            .load::<WordPronunciation>(connection)?);
+    }
+    pronunciations.sort_by_key(|pronunciation| pronunciation.pronunciation_id);
+    let entry_words: HashMap<_, _> = entries.iter().map(|entry| (entry.entry_id, entry.word_id)).collect();
+
+    let mut annotations: HashMap<i32, (Vec<String>, Option<String>)> = HashMap::new();
+    for entry in &entries {
+        let gloss = &mut annotations.entry(entry.word_id).or_default().1;
+        if gloss.is_none() {
+            *gloss = entry.definitions.split('|').map(str::trim).find(|s| !s.is_empty()).map(String::from);
+        }
+    }
+    for pronunciation in pronunciations {
+        let word_pronunciations = &mut annotations.entry(entry_words[&pronunciation.entry_id]).or_default().0;
+        if !word_pronunciations.contains(&pronunciation.pronunciation) {
+            word_pronunciations.push(pronunciation.pronunciation);
+        }
+    }
+    Ok(annotations)
 }
 
+/// Write a stored pronunciation in another system. It is kept as it is if there is
+/// no other system, or it cannot be converted.
+fn render_pronunciation(pronunciation: &str, pronunciation_type: PronunciationType, romanization: Option<Romanization>) -> String {
+    romanization
+        .and_then(|romanization| convert(pronunciation, pronunciation_type.into(), romanization).ok())
+        .unwrap_or_else(|| pronunciation.to_string())
+}
 
-fn get_word_long_result(word_id: i32, connection: &DbConnection) -> Result<WordResult, DieselError> {
+fn get_word_long_result(word_id: i32, options: DisplayOptions, connection: &DbConnection) -> Result<WordResult, DieselError> {
     let word = word::table.filter(word::word_id.eq(word_id))
         .first::<Word>(connection)?;
     let entries = WordEntry::belonging_to(&word).load::<WordEntry>(connection)?;
@@ -129,9 +613,13 @@ fn get_word_long_result(word_id: i32, connection: &DbConnection) -> Result<WordR
                 pronunciations: HashMap::new(),
             };
             for pronunciation in &entry.1 {
+                let rendered = match PronunciationType::from_integer(pronunciation.pronunciation_type) {
+                    Some(pronunciation_type) => render_pronunciation(&pronunciation.pronunciation, pronunciation_type, options.romanization(pronunciation.pronunciation_type)),
+                    None => pronunciation.pronunciation.clone(),
+                };
                 entry_result.pronunciations.entry(pronunciation.pronunciation_type)
                     .or_insert_with(|| Vec::new())
-                    .push(pronunciation.pronunciation.clone());
+                    .push(rendered);
             }
 
             result.entries.entry(entry.0.dictionary_id)
@@ -141,3 +629,31 @@ fn get_word_long_result(word_id: i32, connection: &DbConnection) -> Result<WordR
 
     Ok(result)
 }
+
+#[derive(Serialize)]
+struct ReloadResult {
+    // false if a rebuild was already running
+    started: bool,
+    revision: i32,
+}
+
+/// Rebuild the search index in the background, e.g. after an import. Requires the
+/// `Authorization: Bearer <ADMIN_TOKEN>` header.
+#[post("/admin/reload")]
+pub(crate) async fn reload_search(data: web::Data<AppData>, request: HttpRequest) -> Result<HttpResponse, DictError> {
+    let token = request.headers().get("Authorization")
+        .and_then(|value| value.as_bytes().strip_prefix(b"Bearer "));
+    match (&data.admin_token, token) {
+        (Some(expected), Some(token)) if constant_time_eq(expected.as_bytes(), token) => {}
+        _ => return Err(DictError::Forbidden),
+    }
+
+    let started = data.dict_search.reload(data.database_pool.clone());
+    Ok(HttpResponse::Accepted().json(ReloadResult { started, revision: data.dict_search.revision() }))
+}
+
+/// Compare two byte strings in a time which depends only on their lengths, so that
+/// a token cannot be guessed a byte at a time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (x, y)| difference | (x ^ y)) == 0
+}
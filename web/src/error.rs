@@ -10,6 +10,8 @@ pub enum DictError {
     Search(SearchError),
     Database(DieselError),
     Actix,
+    /// An admin route was called without the admin token.
+    Forbidden,
 }
 impl Error for DictError {}
 
@@ -37,6 +39,9 @@ impl ResponseError for DictError {
             DictError::Actix => {
                 HttpResponse::InternalServerError().json(R::new(format!("{}", self)))
             }
+            DictError::Forbidden => {
+                HttpResponse::Forbidden().json(R::new(format!("{}", self)))
+            }
         }
     }
 }
@@ -49,6 +54,7 @@ impl Display for DictError {
             },
             DictError::Database(e) => write!(fmt, "Database error: {}", e),
             DictError::Actix => write!(fmt, "Actix error"),
+            DictError::Forbidden => write!(fmt, "Forbidden"),
         }
     }
 }
@@ -1,35 +1,66 @@
 use actix_web::{App, HttpServer};
 use database::ConnectionPool;
-use database::search::{DictSearch, load_search};
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::controller::{single_word, pronunciation_search, character_search};
+use crate::controller::{single_word, homophones, pronunciation_search, character_search, component_search, english_search, mixed_search, auto_search, suggest, annotate, convert_pronunciation, radical_list, radical_characters, starting_search, reload_search};
+use crate::reload::{SearchIndex, build_index, watch_imports};
 
 mod error;
 mod controller;
+mod reload;
+
+/// How often to check for a new import, in seconds, if SEARCH_RELOAD_INTERVAL is not defined.
+const DEFAULT_RELOAD_INTERVAL: u64 = 60;
 
 struct AppData {
     database_pool: ConnectionPool,
-    dict_search: Arc<DictSearch>,
+    dict_search: Arc<SearchIndex>,
+    // the token required by admin routes, which are disabled if it is not defined
+    admin_token: Option<String>,
 }
 
 fn main() {
     // the below line calls dotenv().ok()
     let database_pool = database::create_db_pool();
-    let mut dict_search = DictSearch::new();
     println!("indexing data");
-    load_search(&mut dict_search, &database_pool.get_connection());
-    let ptr = Arc::new(dict_search);
+    let ptr = {
+        let connection = &database_pool.get_connection();
+        let revision = database::import_revision(connection).expect("Could not read import revision");
+        let search = build_index(connection).expect("Could not build search index");
+        Arc::new(SearchIndex::new(search, revision))
+    };
+
+    // the index is rebuilt in the background after an import; an interval of 0 disables this
+    let interval = std::env::var("SEARCH_RELOAD_INTERVAL")
+        .map(|s| s.parse().expect("SEARCH_RELOAD_INTERVAL must be a number of seconds"))
+        .unwrap_or(DEFAULT_RELOAD_INTERVAL);
+    if interval > 0 {
+        watch_imports(ptr.clone(), database_pool.clone(), Duration::from_secs(interval));
+    }
+    let admin_token = std::env::var("ADMIN_TOKEN").ok();
 
     let address = std::env::var("BACKEND_ADDRESS").expect("BACKEND_ADDRESS must be defined");
 
     println!("Web service started at {}", address);
     HttpServer::new(move || {
         // AppData is data that is shared throughout the application.
-        App::new().data(AppData { database_pool: database_pool.clone(), dict_search: ptr.clone() })
+        App::new().data(AppData { database_pool: database_pool.clone(), dict_search: ptr.clone(), admin_token: admin_token.clone() })
             .service(pronunciation_search)
             .service(character_search)
+            .service(component_search)
+            .service(english_search)
+            .service(mixed_search)
+            .service(auto_search)
+            .service(suggest)
+            .service(annotate)
+            .service(convert_pronunciation)
+            .service(radical_list)
+            .service(radical_characters)
+            .service(starting_search)
             .service(single_word)
+            .service(homophones)
+            .service(reload_search)
     }).bind(address).unwrap().run().unwrap();
 
     println!("Web service stopped");
@@ -0,0 +1,124 @@
+//! Rebuilding the search index while the server is running, so that the results
+//! of a new import are served without a restart.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use database::diesel::QueryResult;
+use database::search::{load_search, load_snapshot, save_snapshot, DictSearch};
+use database::{ConnectionPool, DbConnection, PoolError};
+
+pub(crate) struct SearchIndex {
+    current: RwLock<Arc<DictSearch>>,
+    // the import revision the current index was built from
+    revision: AtomicI32,
+    reloading: AtomicBool,
+}
+
+impl SearchIndex {
+    pub(crate) fn new(search: DictSearch, revision: i32) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(search)),
+            revision: AtomicI32::new(revision),
+            reloading: AtomicBool::new(false),
+        }
+    }
+    /// The current index. A request keeps the index it started with until it
+    /// finishes, even if a new one is swapped in meanwhile.
+    pub(crate) fn current(&self) -> Arc<DictSearch> {
+        self.current.read().unwrap().clone()
+    }
+    pub(crate) fn revision(&self) -> i32 {
+        self.revision.load(Ordering::SeqCst)
+    }
+    /// Rebuild the index from the database in a background thread, then swap it in.
+    /// Returns false if a rebuild is already running.
+    pub(crate) fn reload(self: &Arc<Self>, pool: ConnectionPool) -> bool {
+        if self.reloading.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        let index = Arc::clone(self);
+        thread::spawn(move || {
+            // allow another reload when this one finishes, even if it panics
+            let _guard = ReloadGuard(&index.reloading);
+            if let Err(e) = index.rebuild(&pool) {
+                println!("Could not reload search index: {}", e);
+            }
+        });
+        true
+    }
+    fn rebuild(&self, pool: &ConnectionPool) -> Result<(), PoolError> {
+        let connection = &pool.try_get_connection()?;
+        // read the revision first, so an import during the rebuild causes another one
+        let revision = database::import_revision(connection)?;
+        println!("rebuilding search index for import revision {}", revision);
+        let search = build_index(connection)?;
+        *self.current.write().unwrap() = Arc::new(search);
+        self.revision.store(revision, Ordering::SeqCst);
+        println!("search index reloaded");
+        Ok(())
+    }
+    /// Reload the index if the database has been imported into since it was built.
+    pub(crate) fn reload_if_stale(self: &Arc<Self>, pool: &ConnectionPool) -> bool {
+        let connection = match pool.try_get_connection() {
+            Ok(connection) => connection,
+            Err(e) => {
+                println!("Could not connect to the database: {}", e);
+                return false;
+            }
+        };
+        match database::import_revision(&connection) {
+            Ok(revision) if revision != self.revision() => self.reload(pool.clone()),
+            Ok(_) => false,
+            Err(e) => {
+                println!("Could not read import revision: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Resets the reloading flag when dropped.
+struct ReloadGuard<'a>(&'a AtomicBool);
+
+impl Drop for ReloadGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Build the index from the database. If SEARCH_SNAPSHOT is defined, the index is
+/// saved there and only rebuilt after an import.
+pub(crate) fn build_index(connection: &DbConnection) -> QueryResult<DictSearch> {
+    let path = match std::env::var_os("SEARCH_SNAPSHOT") {
+        Some(path) => PathBuf::from(path),
+        None => return load_index(connection),
+    };
+    let revision = database::import_revision(connection)?;
+    match load_snapshot(&path, revision) {
+        Ok(search) => return Ok(search),
+        Err(e) => println!("{}, rebuilding search index", e),
+    }
+    let search = load_index(connection)?;
+    if let Err(e) = save_snapshot(&search, revision, &path) {
+        println!("Could not save snapshot: {}", e);
+    }
+    Ok(search)
+}
+
+fn load_index(connection: &DbConnection) -> QueryResult<DictSearch> {
+    let mut search = DictSearch::new();
+    load_search(&mut search, connection)?;
+    Ok(search)
+}
+
+/// Check the import revision every `interval`, reloading the index after an import.
+pub(crate) fn watch_imports(index: Arc<SearchIndex>, pool: ConnectionPool, interval: Duration) {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        index.reload_if_stale(&pool);
+    });
+}